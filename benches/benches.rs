@@ -24,4 +24,102 @@ fn retain(b: &mut test::Bencher) {
         // cloning is relatively fast compared to retain()
         set.clone().retain(|n| n % 2 == 0);
     });
-}
\ No newline at end of file
+}
+
+#[bench]
+fn insert_remove_range(b: &mut test::Bencher) {
+    let mut set = IdSet::with_capacity(10000);
+
+    b.iter(|| {
+        set.insert_range(17..9983);
+        set.remove_range(100..9900);
+        test::black_box(&set);
+    });
+}
+
+#[bench]
+fn ranges(b: &mut test::Bencher) {
+    let set: IdSet = (0..100000).filter(|&n| n % 1000 != 0).collect();
+
+    b.iter(|| {
+        for range in set.ranges() {
+            test::black_box(range);
+        }
+    });
+}
+
+#[bench]
+fn jaccard(b: &mut test::Bencher) {
+    let x: IdSet = (0..10000).filter(|&n| n % 3 == 0).collect();
+    let y: IdSet = (0..10000).filter(|&n| n % 5 == 0).collect();
+
+    b.iter(|| test::black_box(id_set::jaccard(&x, &y)));
+}
+
+#[bench]
+fn bitand_assign_blocks(b: &mut test::Bencher) {
+    let x: IdSet<u64> = (0..100000).filter(|&n| n % 3 == 0).collect();
+    let y: IdSet<u64> = (0..100000).filter(|&n| n % 5 == 0).collect();
+
+    b.iter(|| {
+        let mut set = x.clone();
+        set &= y.blocks();
+        test::black_box(set.len())
+    });
+}
+
+#[bench]
+fn bitand_assign_slice(b: &mut test::Bencher) {
+    let x: IdSet<u64> = (0..100000).filter(|&n| n % 3 == 0).collect();
+    let y: IdSet<u64> = (0..100000).filter(|&n| n % 5 == 0).collect();
+
+    b.iter(|| {
+        let mut set = x.clone();
+        set &= &y;
+        test::black_box(set.len())
+    });
+}
+
+#[bench]
+fn bitxor_assign_blocks(b: &mut test::Bencher) {
+    let x: IdSet<u64> = (0..100000).filter(|&n| n % 3 == 0).collect();
+    let y: IdSet<u64> = (0..100000).filter(|&n| n % 5 == 0).collect();
+
+    b.iter(|| {
+        let mut set = x.clone();
+        set ^= y.blocks();
+        test::black_box(set.len())
+    });
+}
+
+#[bench]
+fn bitxor_assign_slice(b: &mut test::Bencher) {
+    let x: IdSet<u64> = (0..100000).filter(|&n| n % 3 == 0).collect();
+    let y: IdSet<u64> = (0..100000).filter(|&n| n % 5 == 0).collect();
+
+    b.iter(|| {
+        let mut set = x.clone();
+        set ^= &y;
+        test::black_box(set.len())
+    });
+}
+
+#[bench]
+fn bitor_assign_slice_u8(b: &mut test::Bencher) {
+    let x: IdSet<u8> = (0..100000).filter(|&n| n % 3 == 0).collect();
+    let y: IdSet<u8> = (0..100000).filter(|&n| n % 5 == 0).collect();
+
+    b.iter(|| {
+        let mut set = x.clone();
+        set |= &y;
+        test::black_box(set.len())
+    });
+}
+
+#[bench]
+fn intersection_len_u32(b: &mut test::Bencher) {
+    let x: IdSet<u32> = (0..100000).filter(|&n| n % 3 == 0).collect();
+    let y: IdSet<u32> = (0..100000).filter(|&n| n % 5 == 0).collect();
+
+    b.iter(|| test::black_box(x.intersection_len(&y)));
+}
@@ -0,0 +1,153 @@
+use std::ops;
+
+use super::{BitBlock, Block, Id, IdSet, Iter};
+use block::low_mask;
+
+#[derive(Clone, Debug, Default)]
+/// Allocates the lowest free ids from an `IdSet` of live ids.
+///
+/// Each slot has a generation counter which is incremented whenever its id is freed, so stale
+/// handles to a reused id can be detected.
+///
+/// # Examples
+///
+/// ```
+/// # use id_set::IdAllocator;
+/// #
+/// let mut ids = IdAllocator::new();
+/// assert_eq!(ids.alloc(), 0);
+/// assert_eq!(ids.alloc(), 1);
+///
+/// let generation = ids.generation(0);
+/// ids.free(0);
+/// assert_eq!(ids.alloc(), 0);
+/// assert!(!ids.is_current(0, generation));
+/// ```
+pub struct IdAllocator<T: BitBlock = Block> {
+    live: IdSet<T>,
+    generations: Vec<u32>,
+    // All blocks before this one are full.
+    hint: usize,
+}
+
+impl IdAllocator {
+    #[inline]
+    /// Creates an allocator with no live ids.
+    pub fn new() -> Self {
+        IdAllocator::new_general()
+    }
+}
+
+impl<T: BitBlock> IdAllocator<T> {
+    #[inline]
+    /// Creates an allocator with any block type with no live ids.
+    pub fn new_general() -> Self {
+        IdAllocator {
+            live: IdSet::new_general(),
+            generations: Vec::new(),
+            hint: 0,
+        }
+    }
+
+    #[inline]
+    /// Returns the number of live ids.
+    pub fn len(&self) -> usize {
+        self.live.len()
+    }
+
+    #[inline]
+    /// Returns true if there are no live ids.
+    pub fn is_empty(&self) -> bool {
+        self.live.is_empty()
+    }
+
+    #[inline]
+    /// Returns the set of live ids.
+    pub fn live(&self) -> &IdSet<T> {
+        &self.live
+    }
+
+    #[inline]
+    /// Returns true if the given id is allocated.
+    pub fn is_live(&self, id: Id) -> bool {
+        self.live.contains(id)
+    }
+
+    #[inline]
+    /// An iterator over all live ids in increasing order.
+    pub fn iter_live(&self) -> Iter<'_, T> {
+        self.live.iter()
+    }
+
+    #[inline]
+    /// Returns the generation of the given slot, which is the number of times it has been freed.
+    pub fn generation(&self, id: Id) -> u32 {
+        self.generations.get(id).cloned().unwrap_or(0)
+    }
+
+    #[inline]
+    /// Returns true if the id is live and a handle with the given generation is not stale.
+    pub fn is_current(&self, id: Id, generation: u32) -> bool {
+        self.is_live(id) && self.generation(id) == generation
+    }
+
+    /// Allocates and returns the lowest free id.
+    pub fn alloc(&mut self) -> Id {
+        let id = self.next_free(self.hint * T::BITS);
+        self.hint = id / T::BITS;
+        self.live.insert(id);
+        id
+    }
+
+    /// Allocates the lowest run of `n` consecutive free ids.
+    pub fn alloc_range(&mut self, n: usize) -> ops::Range<Id> {
+        let mut start = self.next_free(self.hint * T::BITS);
+        if n == 0 {
+            return start..start;
+        }
+        loop {
+            match self.live.next_after(start) {
+                Some(end) if end - start < n => start = self.next_free(end),
+                _ => break,
+            }
+        }
+        self.live.insert_range(start..start + n);
+        start..start + n
+    }
+
+    /// Frees the given id, returning true if it was live. The generation of the slot is
+    /// incremented.
+    pub fn free(&mut self, id: Id) -> bool {
+        if !self.live.remove(id) {
+            return false;
+        }
+        if self.generations.len() <= id {
+            self.generations.resize(id + 1, 0);
+        }
+        self.generations[id] = self.generations[id].wrapping_add(1);
+        if id / T::BITS < self.hint {
+            self.hint = id / T::BITS;
+        }
+        true
+    }
+
+    /// Returns the smallest free id greater than or equal to `id`.
+    fn next_free(&self, id: Id) -> Id {
+        let blocks = self.live.as_blocks();
+        let (word, bit) = (id / T::BITS, id % T::BITS);
+        if word >= blocks.len() {
+            return id;
+        }
+        let free = !blocks[word] & !low_mask::<T>(bit);
+        if free != T::ZERO {
+            return word * T::BITS + free.trailing_zeros() as usize;
+        }
+        match blocks[word + 1..].iter().position(|&block| block != T::MAX) {
+            Some(offset) => {
+                let word = word + 1 + offset;
+                word * T::BITS + (!blocks[word]).trailing_zeros() as usize
+            }
+            None => blocks.len() * T::BITS,
+        }
+    }
+}
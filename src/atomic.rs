@@ -0,0 +1,220 @@
+use std::{cmp, fmt};
+use std::sync::atomic::{AtomicIsize, AtomicU16, AtomicU32, AtomicU8, Ordering};
+#[cfg(target_has_atomic = "64")]
+use std::sync::atomic::AtomicU64;
+
+use super::{ceil_div, BitBlock, Block, Id, IdSet};
+use block::mask;
+
+/// A block type with a corresponding atomic type, which can be used as the block type of an
+/// `AtomicIdSet`.
+///
+/// This trait is implemented for `u8`, `u16` and `u32`, and for `u64` on targets with 64-bit
+/// atomics.
+pub trait AtomicBlock: BitBlock {
+    /// The atomic version of the block type.
+    type Atomic: Send + Sync + fmt::Debug;
+
+    /// Creates a new atomic block.
+    fn new_atomic(block: Self) -> Self::Atomic;
+
+    /// Loads the value of an atomic block.
+    fn load(atomic: &Self::Atomic, order: Ordering) -> Self;
+
+    /// Stores a value into an atomic block.
+    fn store(atomic: &Self::Atomic, block: Self, order: Ordering);
+
+    /// Bitwise or with an atomic block, returning the previous value.
+    fn fetch_or(atomic: &Self::Atomic, block: Self, order: Ordering) -> Self;
+
+    /// Bitwise and with an atomic block, returning the previous value.
+    fn fetch_and(atomic: &Self::Atomic, block: Self, order: Ordering) -> Self;
+}
+
+macro_rules! impl_atomic_block {
+    ($($ty:ident $atomic:ident)*) => {$(
+        impl AtomicBlock for $ty {
+            type Atomic = $atomic;
+
+            #[inline]
+            fn new_atomic(block: Self) -> Self::Atomic {
+                $atomic::new(block)
+            }
+
+            #[inline]
+            fn load(atomic: &Self::Atomic, order: Ordering) -> Self {
+                atomic.load(order)
+            }
+
+            #[inline]
+            fn store(atomic: &Self::Atomic, block: Self, order: Ordering) {
+                atomic.store(block, order)
+            }
+
+            #[inline]
+            fn fetch_or(atomic: &Self::Atomic, block: Self, order: Ordering) -> Self {
+                atomic.fetch_or(block, order)
+            }
+
+            #[inline]
+            fn fetch_and(atomic: &Self::Atomic, block: Self, order: Ordering) -> Self {
+                atomic.fetch_and(block, order)
+            }
+        }
+    )*}
+}
+
+impl_atomic_block!(u8 AtomicU8 u16 AtomicU16 u32 AtomicU32);
+#[cfg(target_has_atomic = "64")]
+impl_atomic_block!(u64 AtomicU64);
+
+/// A set of `usize` elements with a fixed capacity which can be modified concurrently without
+/// locking.
+///
+/// Each block is stored in an atomic integer, so `insert` and `remove` are single atomic
+/// operations. The set can be converted into an `IdSet` with [`snapshot`](#method.snapshot) to use
+/// the usual set operations.
+///
+/// # Examples
+///
+/// ```
+/// # use id_set::AtomicIdSet;
+/// # use std::thread;
+/// #
+/// let set = AtomicIdSet::new(1000);
+/// thread::scope(|scope| {
+///     for n in 0..4 {
+///         let set = &set;
+///         scope.spawn(move || {
+///             for id in (n..1000).step_by(4) {
+///                 set.insert(id);
+///             }
+///         });
+///     }
+/// });
+/// assert_eq!(set.len(), 1000);
+/// assert_eq!(set.snapshot().len(), 1000);
+/// ```
+pub struct AtomicIdSet<T: AtomicBlock = Block> {
+    blocks: Box<[T::Atomic]>,
+    // The number of elements, updated after each successful insertion or removal. A removal may
+    // update it before the insertion it undid, so it can briefly be negative.
+    len: AtomicIsize,
+}
+
+impl AtomicIdSet {
+    #[inline]
+    /// Creates an empty `AtomicIdSet` which can hold elements less than `capacity`.
+    pub fn new(capacity: usize) -> Self {
+        AtomicIdSet::new_general(capacity)
+    }
+}
+
+impl<T: AtomicBlock> AtomicIdSet<T> {
+    /// Creates an empty `AtomicIdSet` with any block type which can hold elements less than
+    /// `capacity`.
+    pub fn new_general(capacity: usize) -> Self {
+        AtomicIdSet {
+            blocks: (0..ceil_div(capacity, T::BITS)).map(|_| T::new_atomic(T::ZERO)).collect(),
+            len: AtomicIsize::new(0),
+        }
+    }
+
+    #[inline]
+    /// Returns the number of elements the set can hold. This may be larger than the capacity it
+    /// was created with.
+    pub fn capacity(&self) -> usize {
+        self.blocks.len() * T::BITS
+    }
+
+    #[inline]
+    /// Returns the number of distinct elements in the set. While other threads are modifying the
+    /// set this may not reflect their most recent changes.
+    pub fn len(&self) -> usize {
+        cmp::max(self.len.load(Ordering::Relaxed), 0) as usize
+    }
+
+    #[inline]
+    /// Returns true if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    #[inline]
+    /// Inserts the given element into the set, returning true if it was not already in the set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the element is not less than the capacity of the set.
+    pub fn insert(&self, id: Id) -> bool {
+        let (word, bit) = (id / T::BITS, id % T::BITS);
+        let old = T::fetch_or(&self.blocks[word], mask(bit), Ordering::AcqRel);
+        let inserted = old & mask(bit) == T::ZERO;
+        if inserted {
+            self.len.fetch_add(1, Ordering::Relaxed);
+        }
+        inserted
+    }
+
+    #[inline]
+    /// Removes the given element from the set, returning true if it was in the set.
+    pub fn remove(&self, id: Id) -> bool {
+        let (word, bit) = (id / T::BITS, id % T::BITS);
+        let block = match self.blocks.get(word) {
+            Some(block) => block,
+            None => return false,
+        };
+        let old = T::fetch_and(block, !mask::<T>(bit), Ordering::AcqRel);
+        let removed = old & mask(bit) != T::ZERO;
+        if removed {
+            self.len.fetch_sub(1, Ordering::Relaxed);
+        }
+        removed
+    }
+
+    #[inline]
+    /// Returns true if the given element is in the set.
+    pub fn contains(&self, id: Id) -> bool {
+        let (word, bit) = (id / T::BITS, id % T::BITS);
+        match self.blocks.get(word) {
+            Some(block) => T::load(block, Ordering::Acquire) & mask(bit) != T::ZERO,
+            None => false,
+        }
+    }
+
+    /// Removes all elements from the set.
+    pub fn clear(&mut self) {
+        for block in self.blocks.iter() {
+            T::store(block, T::ZERO, Ordering::Relaxed);
+        }
+        self.len = AtomicIsize::new(0);
+    }
+
+    /// Copies the set into an `IdSet`. Each block is loaded separately, so while other threads are
+    /// modifying the set the copy may include some of their changes and not others.
+    pub fn snapshot(&self) -> IdSet<T> {
+        let blocks = self.blocks.iter().map(|block| T::load(block, Ordering::Acquire)).collect();
+        IdSet::from_blocks(blocks)
+    }
+
+    /// Consumes the set and returns its elements as an `IdSet`.
+    pub fn into_set(self) -> IdSet<T> {
+        self.snapshot()
+    }
+}
+
+impl<T: AtomicBlock, const N: usize> From<IdSet<T, N>> for AtomicIdSet<T> {
+    /// Creates an `AtomicIdSet` with the elements of a set and the capacity of its blocks.
+    fn from(set: IdSet<T, N>) -> Self {
+        AtomicIdSet {
+            len: AtomicIsize::new(set.len() as isize),
+            blocks: set.as_blocks().iter().map(|&block| T::new_atomic(block)).collect(),
+        }
+    }
+}
+
+impl<T: AtomicBlock> fmt::Debug for AtomicIdSet<T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.snapshot().iter()).finish()
+    }
+}
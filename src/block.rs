@@ -0,0 +1,145 @@
+use std::{fmt, hash, ops};
+
+/// An unsigned integer type which can be used as the block type of a set.
+///
+/// This trait is sealed and implemented for `u8`, `u16`, `u32`, `u64` and `u128`.
+pub trait BitBlock
+    : Copy
+    + Default
+    + Eq
+    + Ord
+    + hash::Hash
+    + fmt::Debug
+    + Send
+    + Sync
+    + 'static
+    + ops::BitAnd<Output = Self>
+    + ops::BitOr<Output = Self>
+    + ops::BitXor<Output = Self>
+    + ops::Not<Output = Self>
+    + ops::BitAndAssign
+    + ops::BitOrAssign
+    + ops::BitXorAssign
+    + ops::Shl<usize, Output = Self>
+    + ops::Shr<usize, Output = Self>
+    + private::Sealed {
+    /// The number of bits in the block.
+    const BITS: usize;
+    /// The block with no bits set.
+    const ZERO: Self;
+    /// The block with only the least significant bit set.
+    const ONE: Self;
+    /// The block with all bits set.
+    const MAX: Self;
+
+    /// Returns the number of set bits.
+    fn count_ones(self) -> u32;
+
+    /// Returns the number of trailing zero bits.
+    fn trailing_zeros(self) -> u32;
+
+    /// Returns the number of leading zero bits.
+    fn leading_zeros(self) -> u32;
+
+    /// Wrapping subtraction, used to clear the lowest set bit.
+    fn wrapping_sub(self, rhs: Self) -> Self;
+
+    /// Widens the block to a `u128`.
+    fn to_u128(self) -> u128;
+
+    /// Truncates a `u128` to a block.
+    fn from_u128(n: u128) -> Self;
+}
+
+mod private {
+    pub trait Sealed {}
+}
+
+macro_rules! impl_bit_block {
+    ($($ty:ident)*) => {$(
+        impl private::Sealed for $ty {}
+
+        impl BitBlock for $ty {
+            const BITS: usize = $ty::BITS as usize;
+            const ZERO: Self = 0;
+            const ONE: Self = 1;
+            const MAX: Self = $ty::MAX;
+
+            #[inline]
+            fn count_ones(self) -> u32 {
+                $ty::count_ones(self)
+            }
+
+            #[inline]
+            fn trailing_zeros(self) -> u32 {
+                $ty::trailing_zeros(self)
+            }
+
+            #[inline]
+            fn leading_zeros(self) -> u32 {
+                $ty::leading_zeros(self)
+            }
+
+            #[inline]
+            fn wrapping_sub(self, rhs: Self) -> Self {
+                $ty::wrapping_sub(self, rhs)
+            }
+
+            #[inline]
+            fn to_u128(self) -> u128 {
+                self as u128
+            }
+
+            #[inline]
+            fn from_u128(n: u128) -> Self {
+                n as $ty
+            }
+        }
+    )*}
+}
+
+impl_bit_block!(u8 u16 u32 u64 u128);
+
+/// Returns a block with only the given bit set.
+#[inline]
+pub fn mask<T: BitBlock>(bit: usize) -> T {
+    T::ONE << bit
+}
+
+/// Returns a block with all bits below the given bit set.
+#[inline]
+pub fn low_mask<T: BitBlock>(bit: usize) -> T {
+    mask::<T>(bit).wrapping_sub(T::ONE)
+}
+
+/// Remove the least significant bit and return its index.
+#[inline]
+pub fn pop_lsb<T: BitBlock>(n: &mut T) -> usize {
+    let idx = n.trailing_zeros() as usize;
+    *n &= n.wrapping_sub(T::ONE);
+    idx
+}
+
+/// Returns the index of the k-th least significant set bit. The block must have more than k bits
+/// set.
+#[inline]
+pub fn select_bit<T: BitBlock>(mut n: T, k: usize) -> usize {
+    for _ in 0..k {
+        n &= n.wrapping_sub(T::ONE);
+    }
+    n.trailing_zeros() as usize
+}
+
+/// Returns the index of the most significant set bit. The block must be non-zero.
+#[inline]
+pub fn msb<T: BitBlock>(n: T) -> usize {
+    T::BITS - 1 - n.leading_zeros() as usize
+}
+
+/// Remove the most significant bit and return its index.
+#[inline]
+pub fn pop_msb<T: BitBlock>(n: &mut T) -> usize {
+    let idx = msb(*n);
+    *n &= !mask::<T>(idx);
+    idx
+}
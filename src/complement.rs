@@ -0,0 +1,140 @@
+use std::ops;
+
+use super::{ceil_div, count_ones, BitBlock, BlockIter, Blocks, IdSet, IntoBlockIterator};
+use block::low_mask;
+
+impl<T: BitBlock, const N: usize> IdSet<T, N> {
+    #[inline]
+    /// Returns the complement of the set within the elements less than `universe`, without
+    /// allocating a set for the universe.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use id_set::IdSet;
+    /// #
+    /// let set: IdSet = vec![1, 3, 200].into_iter().collect();
+    /// assert_eq!(set.complement(6).collect::<Vec<_>>(), [0, 2, 4, 5]);
+    /// ```
+    pub fn complement(&self, universe: usize) -> BlockIter<Complement<Blocks<'_, T>>> {
+        self.blocks().into_block_iter().complement(universe)
+    }
+
+    /// Replaces the set with its complement within the elements less than `universe`. Elements
+    /// not less than `universe` are removed.
+    pub fn invert(&mut self, universe: usize) {
+        let (nblocks, nbits) = (ceil_div(universe, T::BITS), universe % T::BITS);
+        if self.blocks.len() > nblocks {
+            self.blocks.drain(nblocks).for_each(drop);
+        } else if self.blocks.len() < nblocks {
+            self.blocks.resize(nblocks);
+        }
+        for block in &mut self.blocks[..nblocks] {
+            *block = !*block;
+        }
+        if nbits != 0 {
+            self.blocks[nblocks - 1] &= low_mask(nbits);
+        }
+        self.len = count_ones(&self.blocks);
+    }
+}
+
+impl<B> BlockIter<B>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
+{
+    #[inline]
+    /// Takes the complement of the blocks within the elements less than `universe`.
+    pub fn complement(self, universe: usize) -> BlockIter<Complement<B>> {
+        BlockIter::new(Complement {
+                           inner: self.into_inner(),
+                           len: ceil_div(universe, B::Item::BITS),
+                           last_bits: universe % B::Item::BITS,
+                       })
+    }
+
+    #[inline]
+    /// Pairs the blocks with a universe size, so they can be complemented with the `!` operator.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use id_set::IdSet;
+    /// #
+    /// let a: IdSet = (0..5).collect();
+    /// let b: IdSet = (3..8).collect();
+    /// assert_eq!((!a.union(&b).within(10)).collect::<Vec<_>>(), [8, 9]);
+    /// ```
+    pub fn within(self, universe: usize) -> Within<B> {
+        Within {
+            inner: self,
+            universe,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+/// A block iterator paired with a universe size, created with
+/// [`BlockIter::within`](struct.BlockIter.html#method.within).
+pub struct Within<B> {
+    inner: BlockIter<B>,
+    universe: usize,
+}
+
+impl<B> ops::Not for Within<B>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
+{
+    type Output = BlockIter<Complement<B>>;
+
+    #[inline]
+    fn not(self) -> Self::Output {
+        self.inner.complement(self.universe)
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Takes the complement of a block iterator within a universe.
+pub struct Complement<B> {
+    inner: B,
+    // The number of blocks remaining.
+    len: usize,
+    // The number of bits of the universe in the final block, or zero if it is full.
+    last_bits: usize,
+}
+
+impl<B> Iterator for Complement<B>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
+{
+    type Item = B::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        let block = !self.inner.next().unwrap_or(B::Item::ZERO);
+        if self.len == 0 && self.last_bits != 0 {
+            Some(block & low_mask(self.last_bits))
+        } else {
+            Some(block)
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<B> ExactSizeIterator for Complement<B>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
+{
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+}
@@ -0,0 +1,365 @@
+//! A small query language for combining named sets.
+//!
+//! An expression is made up of set names combined with the operators below, listed from highest
+//! to lowest precedence. Operators on the same row have equal precedence. Binary operators are left
+//! associative, so `a - b & c` means `(a - b) & c`, and parentheses may be used for grouping.
+//!
+//! | Operator         | Meaning                    |
+//! |------------------|----------------------------|
+//! | `!a`             | complement                 |
+//! | `a & b`, `a - b` | intersection, difference   |
+//! | `a ^ b`          | symmetric difference       |
+//! | `a \| b`         | union                      |
+//!
+//! A run of the same operator, such as `a | b | c`, is parsed into a single node over all of its
+//! operands, and a run of `&` and `-` into the intersection of the `&` operands minus the union of
+//! the `-` operands. This keeps long lists of names flat.
+//!
+//! Names may contain letters, digits and the characters `_`, `:` and `.`. They are resolved to sets
+//! by a user-supplied function when the expression is evaluated. Since a set has no upper bound,
+//! the complement is taken within a universe of elements less than a given size.
+//!
+//! To bound the recursion when parsing and evaluating, parentheses and complements may be nested
+//! at most 256 levels deep.
+//!
+//! ```
+//! # use id_set::IdSet;
+//! # use id_set::expr::Expr;
+//! #
+//! let a: IdSet = (0..10).collect();
+//! let b: IdSet = (20..30).collect();
+//! let banned: IdSet = vec![5, 25].into_iter().collect();
+//!
+//! let expr: Expr = "(tags:a | tags:b) & !banned".parse().unwrap();
+//! let result = expr.eval(|name| match name {
+//!                            "tags:a" => Some(&a),
+//!                            "tags:b" => Some(&b),
+//!                            "banned" => Some(&banned),
+//!                            _ => None,
+//!                        },
+//!                        100)
+//!     .unwrap();
+//! assert_eq!(result.collect::<Vec<_>>(),
+//!            (0..10).chain(20..30).filter(|&n| n != 5 && n != 25).collect::<Vec<_>>());
+//!
+//! let err = "a & (b | c".parse::<Expr>().unwrap_err();
+//! assert_eq!(err.to_string(), "unclosed parenthesis at position 4");
+//! ```
+
+use std::{error, fmt};
+use std::str::FromStr;
+
+use super::{BitBlock, DynBlockIter, IdSet, IntoBlockIterator};
+
+#[derive(Clone, Debug, PartialEq, Eq, Hash)]
+/// A parsed set expression.
+pub enum Expr {
+    /// A named set.
+    Name(String),
+    /// The complement of a set within the universe.
+    Not(Box<Expr>),
+    /// The intersection of two or more sets.
+    And(Vec<Expr>),
+    /// The union of two or more sets.
+    Or(Vec<Expr>),
+    /// The symmetric difference of two or more sets.
+    Xor(Vec<Expr>),
+    /// The difference of two sets.
+    Sub(Box<Expr>, Box<Expr>),
+}
+
+impl Expr {
+    /// Parses an expression.
+    pub fn parse(input: &str) -> Result<Self, ParseError> {
+        let mut parser = Parser {
+            input,
+            pos: 0,
+            depth: 0,
+        };
+        let expr = parser.or()?;
+        match parser.peek() {
+            Some((pos, ch)) => Err(ParseError::new(ParseErrorKind::UnexpectedChar(ch), pos)),
+            None => Ok(expr),
+        }
+    }
+
+    /// Evaluates the expression, looking up the sets for names with `resolver`. Complements are
+    /// taken within the elements less than `universe`.
+    ///
+    /// Returns an error holding the name if the resolver returns `None` for any name.
+    pub fn eval<'a, T, F, const N: usize>(&self,
+                                          mut resolver: F,
+                                          universe: usize)
+                                          -> Result<DynBlockIter<'a, T>, EvalError>
+        where T: BitBlock,
+              F: FnMut(&str) -> Option<&'a IdSet<T, N>>
+    {
+        self.eval_with(&mut resolver, universe)
+    }
+
+    fn eval_with<'a, T, F, const N: usize>(&self,
+                                           resolver: &mut F,
+                                           universe: usize)
+                                           -> Result<DynBlockIter<'a, T>, EvalError>
+        where T: BitBlock,
+              F: FnMut(&str) -> Option<&'a IdSet<T, N>>
+    {
+        Ok(match *self {
+               Expr::Name(ref name) => {
+                   match resolver(name) {
+                       Some(set) => set.into_dyn(),
+                       None => return Err(EvalError::UnknownName(name.clone())),
+                   }
+               }
+               Expr::Not(ref expr) => {
+                   (!expr.eval_with(resolver, universe)?.within(universe)).into_dyn()
+               }
+               Expr::And(ref exprs) => {
+                   IdSet::intersection_all(eval_all(exprs, resolver, universe)?).into_dyn()
+               }
+               Expr::Or(ref exprs) => {
+                   IdSet::union_all(eval_all(exprs, resolver, universe)?).into_dyn()
+               }
+               Expr::Xor(ref exprs) => {
+                   symmetric_difference_all(eval_all(exprs, resolver, universe)?)
+               }
+               Expr::Sub(ref l, ref r) => {
+                   (l.eval_with(resolver, universe)? - r.eval_with(resolver, universe)?).into_dyn()
+               }
+           })
+    }
+}
+
+/// Evaluates each of the expressions.
+fn eval_all<'a, T, F, const N: usize>(exprs: &[Expr],
+                                      resolver: &mut F,
+                                      universe: usize)
+                                      -> Result<Vec<DynBlockIter<'a, T>>, EvalError>
+    where T: BitBlock,
+          F: FnMut(&str) -> Option<&'a IdSet<T, N>>
+{
+    exprs.iter().map(|expr| expr.eval_with(resolver, universe)).collect()
+}
+
+/// Takes the symmetric difference of a non-empty list of block iterators. Pairs of iterators are
+/// combined in rounds, so the result is nested logarithmically deep in the number of iterators.
+fn symmetric_difference_all<T: BitBlock>(mut iters: Vec<DynBlockIter<'_, T>>)
+                                         -> DynBlockIter<'_, T> {
+    while iters.len() > 1 {
+        let mut rest = iters.into_iter();
+        let mut pairs = Vec::with_capacity(rest.len() / 2 + 1);
+        while let Some(l) = rest.next() {
+            pairs.push(match rest.next() {
+                           Some(r) => (l ^ r).into_dyn(),
+                           None => l,
+                       });
+        }
+        iters = pairs;
+    }
+    iters.pop().unwrap()
+}
+
+/// Returns the single expression in the list, or `op` applied to all of them.
+fn flatten(mut exprs: Vec<Expr>, op: fn(Vec<Expr>) -> Expr) -> Expr {
+    if exprs.len() == 1 {
+        exprs.pop().unwrap()
+    } else {
+        op(exprs)
+    }
+}
+
+impl FromStr for Expr {
+    type Err = ParseError;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        Expr::parse(input)
+    }
+}
+
+/// The maximum nesting depth of an expression.
+const MAX_DEPTH: usize = 256;
+
+struct Parser<'a> {
+    input: &'a str,
+    pos: usize,
+    // The number of enclosing parentheses and complements.
+    depth: usize,
+}
+
+impl<'a> Parser<'a> {
+    /// Returns the next character that is not whitespace and its position, without consuming it.
+    fn peek(&mut self) -> Option<(usize, char)> {
+        let rest = &self.input[self.pos..];
+        let trimmed = rest.trim_start();
+        self.pos += rest.len() - trimmed.len();
+        trimmed.chars().next().map(|ch| (self.pos, ch))
+    }
+
+    /// Consumes the next character if it is one of the given operators.
+    fn eat(&mut self, ops: &[char]) -> Option<char> {
+        match self.peek() {
+            Some((_, ch)) if ops.contains(&ch) => {
+                self.pos += ch.len_utf8();
+                Some(ch)
+            }
+            _ => None,
+        }
+    }
+
+    /// Enters a nested sub-expression starting at `pos`, returning an error if the nesting is
+    /// too deep.
+    fn enter(&mut self, pos: usize) -> Result<(), ParseError> {
+        self.depth += 1;
+        if self.depth > MAX_DEPTH {
+            return Err(ParseError::new(ParseErrorKind::TooDeep, pos));
+        }
+        Ok(())
+    }
+
+    fn or(&mut self) -> Result<Expr, ParseError> {
+        let mut exprs = vec![self.xor()?];
+        while self.eat(&['|']).is_some() {
+            exprs.push(self.xor()?);
+        }
+        Ok(flatten(exprs, Expr::Or))
+    }
+
+    fn xor(&mut self) -> Result<Expr, ParseError> {
+        let mut exprs = vec![self.and()?];
+        while self.eat(&['^']).is_some() {
+            exprs.push(self.and()?);
+        }
+        Ok(flatten(exprs, Expr::Xor))
+    }
+
+    fn and(&mut self) -> Result<Expr, ParseError> {
+        // Intersection and difference commute, so the run is the intersection of the operands
+        // after `&` minus the union of the operands after `-`.
+        let (mut include, mut exclude) = (vec![self.unary()?], Vec::new());
+        while let Some(op) = self.eat(&['&', '-']) {
+            let expr = self.unary()?;
+            if op == '&' {
+                include.push(expr);
+            } else {
+                exclude.push(expr);
+            }
+        }
+        let expr = flatten(include, Expr::And);
+        Ok(if exclude.is_empty() {
+               expr
+           } else {
+               Expr::Sub(Box::new(expr), Box::new(flatten(exclude, Expr::Or)))
+           })
+    }
+
+    fn unary(&mut self) -> Result<Expr, ParseError> {
+        match self.peek() {
+            Some((pos, '!')) => {
+                self.pos += 1;
+                self.enter(pos)?;
+                let expr = Expr::Not(Box::new(self.unary()?));
+                self.depth -= 1;
+                Ok(expr)
+            }
+            Some((pos, '(')) => {
+                self.pos += 1;
+                self.enter(pos)?;
+                let expr = self.or()?;
+                if self.eat(&[')']).is_none() {
+                    return Err(match self.peek() {
+                                   Some((_, ch)) => {
+                                       ParseError::new(ParseErrorKind::UnexpectedChar(ch),
+                                                       self.pos)
+                                   }
+                                   None => ParseError::new(ParseErrorKind::UnclosedParen, pos),
+                               });
+                }
+                self.depth -= 1;
+                Ok(expr)
+            }
+            Some((start, ch)) if is_name_char(ch) => {
+                let len = self.input[start..]
+                    .find(|ch| !is_name_char(ch))
+                    .unwrap_or(self.input.len() - start);
+                self.pos += len;
+                Ok(Expr::Name(self.input[start..start + len].to_owned()))
+            }
+            Some((pos, ch)) => Err(ParseError::new(ParseErrorKind::UnexpectedChar(ch), pos)),
+            None => Err(ParseError::new(ParseErrorKind::UnexpectedEnd, self.pos)),
+        }
+    }
+}
+
+fn is_name_char(ch: char) -> bool {
+    ch.is_alphanumeric() || ch == '_' || ch == ':' || ch == '.'
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// An error parsing an expression, with the byte offset in the input at which it occurred.
+pub struct ParseError {
+    kind: ParseErrorKind,
+    position: usize,
+}
+
+impl ParseError {
+    fn new(kind: ParseErrorKind, position: usize) -> Self {
+        ParseError { kind, position }
+    }
+
+    #[inline]
+    /// Returns the kind of error.
+    pub fn kind(&self) -> ParseErrorKind {
+        self.kind
+    }
+
+    #[inline]
+    /// Returns the byte offset in the input at which the error occurred.
+    pub fn position(&self) -> usize {
+        self.position
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+/// The kind of a `ParseError`.
+pub enum ParseErrorKind {
+    /// A character that cannot appear at this point in an expression.
+    UnexpectedChar(char),
+    /// The input ended where a name or sub-expression was expected.
+    UnexpectedEnd,
+    /// A parenthesis that was never closed. The position is that of the opening parenthesis.
+    UnclosedParen,
+    /// An expression nested too deeply. The position is that of the parenthesis or complement at
+    /// which the limit was reached.
+    TooDeep,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self.kind {
+            ParseErrorKind::UnexpectedChar(ch) => write!(f, "unexpected character {:?}", ch)?,
+            ParseErrorKind::UnexpectedEnd => write!(f, "unexpected end of expression")?,
+            ParseErrorKind::UnclosedParen => write!(f, "unclosed parenthesis")?,
+            ParseErrorKind::TooDeep => write!(f, "expression nested too deeply")?,
+        }
+        write!(f, " at position {}", self.position)
+    }
+}
+
+impl error::Error for ParseError {}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+/// An error evaluating an expression.
+pub enum EvalError {
+    /// A name that the resolver did not recognise.
+    UnknownName(String),
+}
+
+impl fmt::Display for EvalError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            EvalError::UnknownName(ref name) => write!(f, "unknown set {:?}", name),
+        }
+    }
+}
+
+impl error::Error for EvalError {}
@@ -0,0 +1,530 @@
+use std::{cmp, fmt, iter, ops, slice};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+use std::ops::{Bound, RangeBounds};
+
+use super::{BitBlock, Block, BlockIter, Id, IdSet, IntoBlockIterator};
+use block::low_mask;
+
+#[derive(Clone, Default, PartialEq, Eq, Hash)]
+/// A set of `usize` elements represented by a sorted list of disjoint ranges. Storage required is
+/// proportional to the number of contiguous runs in the set, making it suited to data made up of
+/// long runs of elements.
+///
+/// Set operations merge the range lists of both sets, so they take time proportional to the number
+/// of runs rather than the number of elements. The set can be combined with other sets through its
+/// [`blocks`](#method.blocks).
+///
+/// # Examples
+///
+/// ```
+/// # use id_set::{IdSet, IntervalIdSet};
+/// #
+/// let mut set = IntervalIdSet::new();
+/// set.insert_range(0..1_000_000);
+/// set.remove(10);
+/// set.remove(500);
+/// assert_eq!(set.len(), 999_998);
+/// assert_eq!(set.iter_ranges().collect::<Vec<_>>(), [0..=9, 11..=499, 501..=999_999]);
+///
+/// let other: IdSet = (5..15).collect();
+/// assert_eq!(IntervalIdSet::from(&other).intersection(&set).iter_ranges().collect::<Vec<_>>(),
+///            [5..=9, 11..=14]);
+/// ```
+pub struct IntervalIdSet {
+    // Sorted ranges with a gap between each pair of adjacent ranges. The ranges are inclusive so
+    // that one can end at `usize::MAX`.
+    ranges: Vec<ops::RangeInclusive<Id>>,
+    len: usize,
+}
+
+impl IntervalIdSet {
+    #[inline]
+    /// Creates an empty `IntervalIdSet`.
+    pub fn new() -> Self {
+        IntervalIdSet {
+            ranges: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Creates an `IntervalIdSet` from the elements of any block iterator.
+    pub fn from_blocks<I: IntoBlockIterator>(iter: I) -> Self {
+        let mut set = IntervalIdSet::new();
+        for range in iter.into_block_iter().ranges() {
+            set.push(range.start..=range.end - 1);
+        }
+        set
+    }
+
+    #[inline]
+    /// Returns the number of distinct elements in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    /// Returns true if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    /// Removes all elements from the set.
+    pub fn clear(&mut self) {
+        self.ranges.clear();
+        self.len = 0;
+    }
+
+    #[inline]
+    /// Inserts the given element into the set, returning true if it was not already in the set.
+    pub fn insert(&mut self, id: Id) -> bool {
+        self.insert_range(id..=id) != 0
+    }
+
+    #[inline]
+    /// Removes the given element from the set, returning true if it was in the set.
+    pub fn remove(&mut self, id: Id) -> bool {
+        self.remove_range(id..=id) != 0
+    }
+
+    #[inline]
+    /// Returns true if the given element is in the set.
+    pub fn contains(&self, id: Id) -> bool {
+        let idx = self.ranges.partition_point(|range| *range.end() < id);
+        idx < self.ranges.len() && *self.ranges[idx].start() <= id
+    }
+
+    /// Inserts all elements in the given range into the set, returning the number of elements that
+    /// were not already in the set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is unbounded above.
+    pub fn insert_range<R: RangeBounds<Id>>(&mut self, range: R) -> usize {
+        let (mut start, mut last) = match inclusive_bounds(&range) {
+            Some((start, last)) => (start, last.expect("cannot insert an unbounded range")),
+            None => return 0,
+        };
+        // The ranges overlapping or adjacent to the inserted range are merged with it.
+        let first = self.ranges.partition_point(|range| *range.end() < start.saturating_sub(1));
+        let end = self.ranges.partition_point(|range| range.start().saturating_sub(1) <= last);
+        let merged: usize = self.ranges[first..end].iter().map(range_len).sum();
+        if first < end {
+            start = cmp::min(start, *self.ranges[first].start());
+            last = cmp::max(last, *self.ranges[end - 1].end());
+        }
+        self.ranges.splice(first..end, iter::once(start..=last));
+
+        let inserted = (last - start + 1) - merged;
+        self.len += inserted;
+        inserted
+    }
+
+    /// Removes all elements in the given range from the set, returning the number of elements that
+    /// were in the set.
+    pub fn remove_range<R: RangeBounds<Id>>(&mut self, range: R) -> usize {
+        let (start, last) = match inclusive_bounds(&range) {
+            Some((start, last)) => (start, last.unwrap_or(usize::MAX)),
+            None => return 0,
+        };
+        let first = self.ranges.partition_point(|range| *range.end() < start);
+        let end = self.ranges.partition_point(|range| *range.start() <= last);
+        if first == end {
+            return 0;
+        }
+        let removed = self.ranges[first..end]
+            .iter()
+            .map(|range| overlap(range, start, last))
+            .sum();
+
+        let (head, tail) = (*self.ranges[first].start(), *self.ranges[end - 1].end());
+        let before = if head < start { Some(head..=start - 1) } else { None };
+        let after = if last < tail { Some(last + 1..=tail) } else { None };
+        self.ranges.splice(first..end, before.into_iter().chain(after));
+        self.len -= removed;
+        removed
+    }
+
+    /// Returns true if every element in the given range is in the set. This is always true for an
+    /// empty range and always false for a non-empty range that is unbounded above.
+    pub fn contains_range<R: RangeBounds<Id>>(&self, range: R) -> bool {
+        let (start, last) = match inclusive_bounds(&range) {
+            Some((start, Some(last))) => (start, last),
+            Some((_, None)) => return false,
+            None => return true,
+        };
+        let idx = self.ranges.partition_point(|range| *range.end() < start);
+        idx < self.ranges.len() && *self.ranges[idx].start() <= start &&
+        last <= *self.ranges[idx].end()
+    }
+
+    /// Returns true if any element in the given range is in the set.
+    pub fn intersects_range<R: RangeBounds<Id>>(&self, range: R) -> bool {
+        let (start, last) = match inclusive_bounds(&range) {
+            Some((start, last)) => (start, last.unwrap_or(usize::MAX)),
+            None => return false,
+        };
+        let idx = self.ranges.partition_point(|range| *range.end() < start);
+        idx < self.ranges.len() && *self.ranges[idx].start() <= last
+    }
+
+    #[inline]
+    /// Returns the smallest element in the set, or `None` if the set is empty.
+    pub fn first(&self) -> Option<Id> {
+        self.ranges.first().map(|range| *range.start())
+    }
+
+    #[inline]
+    /// Returns the largest element in the set, or `None` if the set is empty.
+    pub fn last(&self) -> Option<Id> {
+        self.ranges.last().map(|range| *range.end())
+    }
+
+    #[inline]
+    /// An iterator over all elements in increasing order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            ranges: self.ranges.iter(),
+            // An empty range.
+            current: ops::RangeInclusive::new(1, 0),
+            len: self.len,
+        }
+    }
+
+    #[inline]
+    /// An iterator over the maximal runs of elements in the set in increasing order.
+    pub fn iter_ranges(&self) -> iter::Cloned<slice::Iter<'_, ops::RangeInclusive<Id>>> {
+        self.ranges.iter().cloned()
+    }
+
+    #[inline]
+    /// Returns an iterator over the blocks of the set, as if it were stored as a bit vector with
+    /// the given block type. This allows the set to be combined with any other set.
+    pub fn blocks<T: BitBlock>(&self) -> BlockIter<Blocks<'_, T>> {
+        let end = self.ranges.last().map_or(0, |range| range.end() / T::BITS + 1);
+        BlockIter::new(Blocks {
+                           ranges: &self.ranges,
+                           range: 0,
+                           idx: 0,
+                           end,
+                           marker: PhantomData,
+                       })
+    }
+
+    /// Appends a range which does not start before the end of the last range.
+    #[inline]
+    fn push(&mut self, range: ops::RangeInclusive<Id>) {
+        self.len += range_len(&range);
+        match self.ranges.last_mut() {
+            Some(last) if *last.end() + 1 == *range.start() => {
+                *last = *last.start()..=*range.end();
+            }
+            _ => self.ranges.push(range),
+        }
+    }
+
+    /// Merges the range lists of two sets, keeping the parts where `f(in_self, in_other)` is true.
+    fn combine<F>(&self, other: &IntervalIdSet, f: F) -> IntervalIdSet
+        where F: Fn(bool, bool) -> bool
+    {
+        let (lhs, rhs) = (&self.ranges[..], &other.ranges[..]);
+        let (mut i, mut j, mut pos) = (0, 0, 0);
+        let mut set = IntervalIdSet::new();
+        loop {
+            // The ranges at i and j are the first to end at or after pos.
+            let in_lhs = i < lhs.len() && *lhs[i].start() <= pos;
+            let in_rhs = j < rhs.len() && *rhs[j].start() <= pos;
+            let last_lhs = lhs.get(i).map(|range| boundary(range, in_lhs));
+            let last_rhs = rhs.get(j).map(|range| boundary(range, in_rhs));
+            let last = match (last_lhs, last_rhs) {
+                (Some(l), Some(r)) => cmp::min(l, r),
+                (Some(last), None) | (None, Some(last)) => last,
+                (None, None) => break,
+            };
+            if f(in_lhs, in_rhs) {
+                set.push(pos..=last);
+            }
+            if i < lhs.len() && *lhs[i].end() <= last {
+                i += 1;
+            }
+            if j < rhs.len() && *rhs[j].end() <= last {
+                j += 1;
+            }
+            pos = match last.checked_add(1) {
+                Some(pos) => pos,
+                None => break,
+            };
+        }
+        set
+    }
+
+    /// Takes the union of the set with another. Equivalent to `self | other`.
+    pub fn union(&self, other: &IntervalIdSet) -> IntervalIdSet {
+        self.combine(other, |l, r| l || r)
+    }
+
+    /// Takes the intersection of the set with another. Equivalent to `self & other`.
+    pub fn intersection(&self, other: &IntervalIdSet) -> IntervalIdSet {
+        self.combine(other, |l, r| l && r)
+    }
+
+    /// Takes the difference of the set with another. Equivalent to `self - other`.
+    pub fn difference(&self, other: &IntervalIdSet) -> IntervalIdSet {
+        self.combine(other, |l, r| l && !r)
+    }
+
+    /// Takes the symmetric difference of the set with another. Equivalent to `self ^ other`.
+    pub fn symmetric_difference(&self, other: &IntervalIdSet) -> IntervalIdSet {
+        self.combine(other, |l, r| l != r)
+    }
+
+    /// Take the union of the set inplace with another set. Equivalent to `*self |= other`.
+    pub fn inplace_union(&mut self, other: &IntervalIdSet) {
+        *self = self.union(other);
+    }
+
+    /// Take the intersection of the set inplace with another set. Equivalent to `*self &= other`.
+    pub fn inplace_intersection(&mut self, other: &IntervalIdSet) {
+        *self = self.intersection(other);
+    }
+
+    /// Take the difference of the set inplace with another set. Equivalent to `*self -= other`.
+    pub fn inplace_difference(&mut self, other: &IntervalIdSet) {
+        *self = self.difference(other);
+    }
+
+    /// Take the symmetric difference of the set inplace with another set. Equivalent to
+    /// `*self ^= other`.
+    pub fn inplace_symmetric_difference(&mut self, other: &IntervalIdSet) {
+        *self = self.symmetric_difference(other);
+    }
+
+    /// Returns true if the sets are disjoint.
+    pub fn is_disjoint(&self, other: &IntervalIdSet) -> bool {
+        self.intersection(other).is_empty()
+    }
+
+    /// Returns true if self is a superset of other.
+    pub fn is_superset(&self, other: &IntervalIdSet) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Returns true if self is a subset of other.
+    pub fn is_subset(&self, other: &IntervalIdSet) -> bool {
+        self.len <= other.len && self.difference(other).is_empty()
+    }
+}
+
+/// Returns the number of elements in `range`.
+#[inline]
+fn range_len(range: &ops::RangeInclusive<Id>) -> usize {
+    range.end() - range.start() + 1
+}
+
+/// Returns the number of elements of `range` in `start..=last`, which must overlap it.
+#[inline]
+fn overlap(range: &ops::RangeInclusive<Id>, start: Id, last: Id) -> usize {
+    cmp::min(*range.end(), last) - cmp::max(*range.start(), start) + 1
+}
+
+/// Returns the last element before the next boundary of a range, which is its end if it contains
+/// the current position and the element before its start otherwise.
+#[inline]
+fn boundary(range: &ops::RangeInclusive<Id>, contains: bool) -> Id {
+    if contains { *range.end() } else { range.start() - 1 }
+}
+
+/// Returns the first and last elements of a non-empty range, with `None` as the last element if the
+/// range is unbounded above, or `None` if the range is empty.
+fn inclusive_bounds<R: RangeBounds<Id>>(range: &R) -> Option<(Id, Option<Id>)> {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.checked_add(1)?,
+        Bound::Unbounded => 0,
+    };
+    let last = match range.end_bound() {
+        Bound::Included(&end) => Some(end),
+        Bound::Excluded(&end) => Some(end.checked_sub(1)?),
+        Bound::Unbounded => None,
+    };
+    match last {
+        Some(last) if last < start => None,
+        _ => Some((start, last)),
+    }
+}
+
+impl fmt::Debug for IntervalIdSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.ranges.iter()).finish()
+    }
+}
+
+impl<'a, T: BitBlock, const N: usize> From<&'a IdSet<T, N>> for IntervalIdSet {
+    #[inline]
+    fn from(set: &'a IdSet<T, N>) -> Self {
+        IntervalIdSet::from_blocks(set)
+    }
+}
+
+impl<'a, T: BitBlock, const N: usize> From<&'a IntervalIdSet> for IdSet<T, N> {
+    fn from(set: &'a IntervalIdSet) -> Self {
+        let mut result = IdSet::with_capacity_general(set.last().map_or(0, |id| id + 1));
+        for range in set.iter_ranges() {
+            result.insert_range(range);
+        }
+        result
+    }
+}
+
+impl Extend<Id> for IntervalIdSet {
+    #[inline]
+    fn extend<I: IntoIterator<Item = Id>>(&mut self, iter: I) {
+        for id in iter {
+            self.insert(id);
+        }
+    }
+}
+
+impl FromIterator<Id> for IntervalIdSet {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = Id>>(iter: I) -> Self {
+        let mut set = IntervalIdSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<'a> IntoIterator for &'a IntervalIdSet {
+    type Item = Id;
+    type IntoIter = Iter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoBlockIterator for &'a IntervalIdSet {
+    type Block = Block;
+    type Blocks = Blocks<'a>;
+
+    #[inline]
+    fn into_block_iter(self) -> BlockIter<Self::Blocks> {
+        self.blocks()
+    }
+}
+
+macro_rules! interval_ops {
+    ($($op:ident $method:ident $assign:ident $assign_method:ident $set_method:ident,)*) => {$(
+        impl<'a, 'b> ops::$op<&'b IntervalIdSet> for &'a IntervalIdSet {
+            type Output = IntervalIdSet;
+
+            #[inline]
+            fn $method(self, other: &'b IntervalIdSet) -> IntervalIdSet {
+                self.$set_method(other)
+            }
+        }
+
+        impl<'a> ops::$assign<&'a IntervalIdSet> for IntervalIdSet {
+            #[inline]
+            fn $assign_method(&mut self, other: &'a IntervalIdSet) {
+                *self = self.$set_method(other);
+            }
+        }
+    )*}
+}
+
+interval_ops! {
+    BitOr bitor BitOrAssign bitor_assign union,
+    BitAnd bitand BitAndAssign bitand_assign intersection,
+    BitXor bitxor BitXorAssign bitxor_assign symmetric_difference,
+    Sub sub SubAssign sub_assign difference,
+}
+
+#[derive(Clone, Debug)]
+/// An iterator over the elements of an `IntervalIdSet` in increasing order.
+pub struct Iter<'a> {
+    ranges: slice::Iter<'a, ops::RangeInclusive<Id>>,
+    current: ops::RangeInclusive<Id>,
+    len: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Id;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.current.is_empty() {
+            self.current = self.ranges.next()?.clone();
+        }
+        self.len -= 1;
+        self.current.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+#[derive(Clone, Debug)]
+/// An iterator over the blocks of an `IntervalIdSet`.
+pub struct Blocks<'a, T = Block> {
+    ranges: &'a [ops::RangeInclusive<Id>],
+    // The index of the first range which ends after the start of the next block.
+    range: usize,
+    // The index of the next block.
+    idx: usize,
+    end: usize,
+    marker: PhantomData<T>,
+}
+
+impl<'a, T: BitBlock> Iterator for Blocks<'a, T> {
+    type Item = T;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.idx == self.end {
+            return None;
+        }
+        // The first and last elements of the block.
+        let (lo, hi) = (self.idx * T::BITS, self.idx * T::BITS + (T::BITS - 1));
+        self.idx += 1;
+
+        let mut block = T::ZERO;
+        while let Some(range) = self.ranges.get(self.range) {
+            if *range.start() > hi {
+                break;
+            }
+            let start = cmp::max(*range.start(), lo) - lo;
+            let last = cmp::min(*range.end(), hi) - lo;
+            block |= !low_mask::<T>(start) & (T::MAX >> (T::BITS - 1 - last));
+            if *range.end() > hi {
+                break;
+            }
+            self.range += 1;
+        }
+        Some(block)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a, T: BitBlock> ExactSizeIterator for Blocks<'a, T> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.end - self.idx
+    }
+}
@@ -34,49 +34,155 @@
 
 #![deny(missing_docs, missing_copy_implementations, missing_debug_implementations)]
 
+#[cfg(feature = "rayon")]
+extern crate rayon as rayon_crate;
+#[cfg(feature = "serde")]
+extern crate serde as serde_crate;
+
 #[cfg(test)]
 mod tests;
+mod allocator;
+mod atomic;
+mod block;
+mod complement;
+pub mod expr;
+mod interval;
+mod map;
+mod matrix;
+mod multi;
+#[cfg(feature = "rayon")]
+pub mod rayon;
+mod rank;
+#[cfg(feature = "serde")]
+pub mod serde;
+mod simd;
+mod similarity;
+mod sparse;
 mod store;
-
+mod typed;
+mod wire;
+
+pub use allocator::IdAllocator;
+pub use atomic::{AtomicBlock, AtomicIdSet};
+pub use block::BitBlock;
+pub use complement::{Complement, Within};
+pub use interval::{IntervalIdSet, Iter as IntervalIter, Blocks as IntervalBlocks};
+pub use map::{Entry, IdMap, OccupiedEntry, VacantEntry, Iter as MapIter, IterMut as MapIterMut,
+              IntoIter as MapIntoIter};
+pub use matrix::BitMatrix;
+pub use multi::{IntersectionAll, Threshold, UnionAll};
+pub use rank::RankIndex;
+pub use similarity::{dice, hamming_distance, jaccard, overlap_coefficient};
+pub use sparse::{SparseIdSet, Iter as SparseIter, Blocks as SparseBlocks};
 pub use store::{Iter as Blocks, IntoIter as IntoBlocks};
+pub use typed::{IdLike, IntoTypedBlockIterator, TypedBlockIter, TypedIdSet, Iter as TypedIter};
+pub use wire::ReadError;
 
-use std::{cmp, fmt, iter, ops, usize};
+use std::{cmp, fmt, iter, ops};
 use std::iter::FromIterator;
+use std::ops::{Bound, RangeBounds};
 
+use block::{mask, low_mask, msb, pop_lsb, pop_msb, select_bit};
 use store::BlockStore;
 
 /// The element type of the set.
 pub type Id = usize;
 
-/// The block type of the underlying representation.
+/// The default block type of the underlying representation.
 pub type Block = u32;
 
-/// The number of bits in the block type.
+/// The number of bits in the default block type.
 pub const BITS: usize = 32;
 
-#[inline]
-fn mask(bit: usize) -> Block {
-    (1 as Block) << bit
-}
+/// The default number of blocks stored inline by an `IdSet`, chosen so that a set of the default
+/// block type fits into the 196-bit footprint of a vector.
+pub const DEFAULT_INLINE: usize = 196 / BITS;
 
 /// Given n and k return the largest integer m such that m*k <= n
 #[inline]
+// `usize::is_multiple_of` needs Rust 1.87.
+#[allow(clippy::manual_is_multiple_of)]
 fn ceil_div(n: usize, k: usize) -> usize {
     if n % k == 0 { n / k } else { n / k + 1 }
 }
 
-/// Remove the least significant bit and return its index.
+/// Returns the total number of set bits in the blocks.
 #[inline]
-fn pop_lsb(n: &mut Block) -> usize {
-    let idx = n.trailing_zeros() as usize;
-    *n &= *n - 1;
-    idx
+fn count_ones<T: BitBlock>(blocks: &[T]) -> usize {
+    blocks.iter().map(|block| block.count_ones() as usize).sum()
+}
+
+/// Returns the number of set bits in the intersection of two block slices. The lengths of both
+/// sets are known, so the sizes of all other combinations follow from this.
+#[inline]
+fn intersection_count<T: BitBlock>(lhs: &[T], rhs: &[T]) -> usize {
+    let n = cmp::min(lhs.len(), rhs.len());
+    simd::count(&lhs[..n], &rhs[..n], simd::Op::And)
+}
+
+/// Converts a range into a half-open pair of bounds. An end of `None` means the range is unbounded
+/// above.
+#[inline]
+fn range_bounds<R: RangeBounds<Id>>(range: &R) -> (Id, Option<Id>) {
+    let start = match range.start_bound() {
+        Bound::Included(&start) => start,
+        Bound::Excluded(&start) => start.saturating_add(1),
+        Bound::Unbounded => 0,
+    };
+    let end = match range.end_bound() {
+        Bound::Included(&end) => Some(end.checked_add(1).expect("range end overflows usize")),
+        Bound::Excluded(&end) => Some(end),
+        Bound::Unbounded => None,
+    };
+    (start, end)
+}
+
+/// Returns the index and mask of every block overlapping the non-empty range `start..end`.
+#[inline]
+fn range_masks<T: BitBlock>(start: Id, end: Id) -> impl Iterator<Item = (usize, T)> {
+    let (first, last) = (start / T::BITS, (end - 1) / T::BITS);
+    let lo = !low_mask::<T>(start % T::BITS);
+    let hi = T::MAX >> (T::BITS - 1 - (end - 1) % T::BITS);
+    (first..last + 1).map(move |word| {
+        let mut mask = T::MAX;
+        if word == first {
+            mask &= lo;
+        }
+        if word == last {
+            mask &= hi;
+        }
+        (word, mask)
+    })
 }
 
 /// A set of `usize` elements represented by a bit vector. Storage required is proportional to the
-/// maximum element in the set.
-pub struct IdSet {
-    blocks: BlockStore,
+/// maximum element in the set; see [`SparseIdSet`] for sets with large, sparse elements and
+/// [`IntervalIdSet`] for sets made up of long runs.
+///
+/// The block type `T` of the underlying representation defaults to `u32`, but any [`BitBlock`]
+/// type may be used. Wider blocks process more elements per step in set operations.
+///
+/// Up to `N` blocks are stored inline before the set spills to the heap. Sets with different
+/// inline sizes can still be combined through [`IntoBlockIterator`].
+///
+/// ```
+/// # use id_set::IdSet;
+/// #
+/// let mut set = IdSet::<u64>::new_general();
+/// set.insert(100);
+/// assert_eq!(set.as_blocks()[1], 1 << 36);
+///
+/// let big: IdSet<u64, 16> = (0..1000).collect();
+/// assert_eq!(big.capacity(), 1024);
+/// assert_eq!((&big & &set).into_set(), set);
+/// ```
+///
+/// [`BitBlock`]: trait.BitBlock.html
+/// [`IntoBlockIterator`]: trait.IntoBlockIterator.html
+/// [`SparseIdSet`]: struct.SparseIdSet.html
+/// [`IntervalIdSet`]: struct.IntervalIdSet.html
+pub struct IdSet<T: BitBlock = Block, const N: usize = DEFAULT_INLINE> {
+    blocks: BlockStore<T, N>,
     // The number of set bits in the set. Since all elements are distinct usize values, it can
     // always fit in a usize.
     len: usize,
@@ -86,6 +192,47 @@ impl IdSet {
     #[inline]
     /// Creates an empty `IdSet`.
     pub fn new() -> Self {
+        IdSet::new_general()
+    }
+
+    #[inline]
+    /// Creates a `IdSet` filled with all elements from 0 to n.
+    pub fn new_filled(n: usize) -> Self {
+        IdSet::new_filled_general(n)
+    }
+
+    #[inline]
+    /// Creates a empty `IdSet` that can hold elements up to n before reallocating.
+    pub fn with_capacity(n: usize) -> Self {
+        IdSet::with_capacity_general(n)
+    }
+
+    #[cfg(test)]
+    fn from_bytes(bytes: &[Block]) -> Self {
+        IdSet::from_block_slice(bytes)
+    }
+}
+
+impl<T: BitBlock, const N: usize> IdSet<T, N> {
+    #[inline]
+    /// Creates a `IdSet` from the blocks of its underlying representation, reusing the vector's
+    /// allocation. Element `i` is in the set if bit `i % T::BITS` of block `i / T::BITS` is set.
+    pub fn from_blocks(blocks: Vec<T>) -> Self {
+        IdSet::from(blocks)
+    }
+
+    #[inline]
+    /// Creates a `IdSet` by copying the blocks of its underlying representation.
+    pub fn from_block_slice(blocks: &[T]) -> Self {
+        IdSet {
+            blocks: BlockStore::from_iter(blocks),
+            len: count_ones(blocks),
+        }
+    }
+
+    #[inline]
+    /// Creates an empty `IdSet` with any block type and inline size.
+    pub fn new_general() -> Self {
         IdSet {
             blocks: BlockStore::new(),
             len: 0,
@@ -93,39 +240,32 @@ impl IdSet {
     }
 
     #[inline]
-    /// Creates a `IdSet` filled with all elements from 0 to n.
-    pub fn new_filled(n: usize) -> Self {
-        let (nwords, nbits) = (n / BITS, n % BITS);
-        let blocks: BlockStore = if nbits != 0 {
-            iter::repeat(!0)
+    // `iter::repeat_n` needs Rust 1.82.
+    #[allow(clippy::manual_repeat_n)]
+    /// Creates a `IdSet` with any block type and inline size filled with all elements from 0 to n.
+    pub fn new_filled_general(n: usize) -> Self {
+        let (nwords, nbits) = (n / T::BITS, n % T::BITS);
+        let blocks: BlockStore<T, N> = if nbits != 0 {
+            iter::repeat(T::MAX)
                 .take(nwords)
-                .chain(iter::once(mask(nbits) - 1))
+                .chain(iter::once(low_mask(nbits)))
                 .collect()
         } else {
-            iter::repeat(!0).take(nwords).collect()
+            iter::repeat(T::MAX).take(nwords).collect()
         };
         IdSet { blocks, len: n }
     }
 
     #[inline]
-    /// Creates a empty `IdSet` that can hold elements up to n before reallocating.
-    pub fn with_capacity(n: usize) -> Self {
+    /// Creates a empty `IdSet` with any block type and inline size that can hold elements up to n
+    /// before reallocating.
+    pub fn with_capacity_general(n: usize) -> Self {
         IdSet {
-            blocks: BlockStore::with_capacity(ceil_div(n, BITS)),
+            blocks: BlockStore::with_capacity(ceil_div(n, T::BITS)),
             len: 0,
         }
     }
 
-    #[cfg(test)]
-    fn from_bytes(bytes: &[Block]) -> Self {
-        let blocks = BlockStore::from_iter(bytes);
-        let len = bytes
-            .iter()
-            .map(|&word| word.count_ones() as usize)
-            .sum();
-        IdSet { blocks, len }
-    }
-
     #[inline]
     /// Returns the number of distinct elements in the set.
     pub fn len(&self) -> usize {
@@ -142,13 +282,13 @@ impl IdSet {
     /// Returns capacity of the set. Inserting any elements less than this will not cause
     /// reallocation.
     pub fn capacity(&self) -> usize {
-        self.blocks.capacity().saturating_mul(BITS)
+        self.blocks.capacity().saturating_mul(T::BITS)
     }
 
     #[inline]
     /// Resizes the set such that `capacity() >= cap`.
     pub fn reserve(&mut self, cap: usize) {
-        self.blocks.reserve(ceil_div(cap, BITS));
+        self.blocks.reserve(ceil_div(cap, T::BITS));
     }
 
     #[inline]
@@ -167,11 +307,11 @@ impl IdSet {
     #[inline]
     /// Inserts the given element into the set, returning true if it was not already in the set.
     pub fn insert(&mut self, id: Id) -> bool {
-        let (word, bit) = (id / BITS, id % BITS);
+        let (word, bit) = (id / T::BITS, id % T::BITS);
         let mask = mask(bit);
 
         if word < self.blocks.len() {
-            if (self.blocks[word] & mask) == 0 {
+            if (self.blocks[word] & mask) == T::ZERO {
                 self.blocks[word] |= mask;
                 self.len += 1;
                 true
@@ -189,11 +329,11 @@ impl IdSet {
     #[inline]
     /// Removes the given element from the set, returning true if it was in the set.
     pub fn remove(&mut self, id: Id) -> bool {
-        let (word, bit) = (id / BITS, id % BITS);
+        let (word, bit) = (id / T::BITS, id % T::BITS);
         let mask = mask(bit);
 
         if word < self.blocks.len() {
-            if (self.blocks[word] & mask) != 0 {
+            if (self.blocks[word] & mask) != T::ZERO {
                 self.blocks[word] &= !mask;
                 self.len -= 1;
                 true
@@ -208,15 +348,198 @@ impl IdSet {
     #[inline]
     /// Returns true if the given element is in the set.
     pub fn contains(&self, id: Id) -> bool {
-        let (word, bit) = (id / BITS, id % BITS);
+        let (word, bit) = (id / T::BITS, id % T::BITS);
 
         if word < self.blocks.len() {
-            (self.blocks[word] & mask(bit)) != 0
+            (self.blocks[word] & mask(bit)) != T::ZERO
         } else {
             false
         }
     }
 
+    /// Inserts all elements in the given range into the set, returning the number of elements that
+    /// were not already in the set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the range is unbounded above.
+    pub fn insert_range<R: RangeBounds<Id>>(&mut self, range: R) -> usize {
+        let (start, end) = range_bounds(&range);
+        let end = end.expect("cannot insert an unbounded range");
+        if start >= end {
+            return 0;
+        }
+        let nblocks = ceil_div(end, T::BITS);
+        if self.blocks.len() < nblocks {
+            self.blocks.resize(nblocks);
+        }
+
+        let mut inserted = 0;
+        for (word, mask) in range_masks::<T>(start, end) {
+            let block = &mut self.blocks[word];
+            inserted += (mask & !*block).count_ones() as usize;
+            *block |= mask;
+        }
+        self.len += inserted;
+        inserted
+    }
+
+    /// Removes all elements in the given range from the set, returning the number of elements that
+    /// were in the set.
+    pub fn remove_range<R: RangeBounds<Id>>(&mut self, range: R) -> usize {
+        let (start, end) = range_bounds(&range);
+        let end = cmp::min(end.unwrap_or(usize::MAX),
+                           self.blocks.len().saturating_mul(T::BITS));
+        if start >= end {
+            return 0;
+        }
+
+        let mut removed = 0;
+        for (word, mask) in range_masks::<T>(start, end) {
+            let block = &mut self.blocks[word];
+            removed += (mask & *block).count_ones() as usize;
+            *block &= !mask;
+        }
+        self.len -= removed;
+        removed
+    }
+
+    /// Returns true if every element in the given range is in the set. This is always true for an
+    /// empty range and always false for a non-empty range that is unbounded above.
+    pub fn contains_range<R: RangeBounds<Id>>(&self, range: R) -> bool {
+        let (start, end) = range_bounds(&range);
+        let end = match end {
+            Some(end) if start >= end => return true,
+            Some(end) => end,
+            None => return false,
+        };
+        if ceil_div(end, T::BITS) > self.blocks.len() || end - start > self.len {
+            return false;
+        }
+        range_masks::<T>(start, end).all(|(word, mask)| self.blocks[word] & mask == mask)
+    }
+
+    /// Returns true if any element in the given range is in the set.
+    pub fn intersects_range<R: RangeBounds<Id>>(&self, range: R) -> bool {
+        let (start, end) = range_bounds(&range);
+        let end = cmp::min(end.unwrap_or(usize::MAX),
+                           self.blocks.len().saturating_mul(T::BITS));
+        if start >= end {
+            return false;
+        }
+        range_masks::<T>(start, end).any(|(word, mask)| self.blocks[word] & mask != T::ZERO)
+    }
+
+    /// Returns the smallest element in the set, or `None` if the set is empty.
+    pub fn first(&self) -> Option<Id> {
+        if self.len == 0 {
+            return None;
+        }
+        self.next_from(0)
+    }
+
+    /// Returns the largest element in the set, or `None` if the set is empty.
+    pub fn last(&self) -> Option<Id> {
+        if self.len == 0 {
+            return None;
+        }
+        self.prev_until(self.blocks.len() * T::BITS)
+    }
+
+    /// Returns the smallest element in the set greater than the given element.
+    pub fn next_after(&self, id: Id) -> Option<Id> {
+        id.checked_add(1).and_then(|id| self.next_from(id))
+    }
+
+    /// Returns the largest element in the set less than the given element.
+    pub fn prev_before(&self, id: Id) -> Option<Id> {
+        self.prev_until(id)
+    }
+
+    /// Removes and returns the smallest element in the set.
+    pub fn pop_first(&mut self) -> Option<Id> {
+        let id = self.first()?;
+        self.remove(id);
+        Some(id)
+    }
+
+    /// Removes and returns the largest element in the set.
+    pub fn pop_last(&mut self) -> Option<Id> {
+        let id = self.last()?;
+        self.remove(id);
+        Some(id)
+    }
+
+    /// Returns the smallest element greater than or equal to `id`.
+    fn next_from(&self, id: Id) -> Option<Id> {
+        let (word, bit) = (id / T::BITS, id % T::BITS);
+        if word >= self.blocks.len() {
+            return None;
+        }
+        let block = self.blocks[word] & !low_mask::<T>(bit);
+        if block != T::ZERO {
+            return Some(word * T::BITS + block.trailing_zeros() as usize);
+        }
+        self.blocks[word + 1..]
+            .iter()
+            .position(|&block| block != T::ZERO)
+            .map(|offset| {
+                let word = word + 1 + offset;
+                word * T::BITS + self.blocks[word].trailing_zeros() as usize
+            })
+    }
+
+    /// Returns the largest element less than `id`.
+    fn prev_until(&self, id: Id) -> Option<Id> {
+        let (mut word, bit) = (id / T::BITS, id % T::BITS);
+        if word >= self.blocks.len() {
+            word = self.blocks.len();
+        } else {
+            let block = self.blocks[word] & low_mask(bit);
+            if block != T::ZERO {
+                return Some(word * T::BITS + msb(block));
+            }
+        }
+        self.blocks[..word]
+            .iter()
+            .rposition(|&block| block != T::ZERO)
+            .map(|word| word * T::BITS + msb(self.blocks[word]))
+    }
+
+    /// Returns the number of elements in the set less than the given element.
+    ///
+    /// This takes time linear in `id`. Use a [`RankIndex`] to answer many queries on a large set.
+    ///
+    /// [`RankIndex`]: struct.RankIndex.html
+    pub fn rank(&self, id: Id) -> usize {
+        let (word, bit) = (id / T::BITS, id % T::BITS);
+        if word >= self.blocks.len() {
+            return self.len;
+        }
+        count_ones(&self.blocks[..word]) + (self.blocks[word] & low_mask(bit)).count_ones() as usize
+    }
+
+    /// Returns the k-th smallest element of the set, counting from zero, or `None` if the set has
+    /// k or fewer elements.
+    ///
+    /// This takes time linear in the result. Use a [`RankIndex`] to answer many queries on a large
+    /// set.
+    ///
+    /// [`RankIndex`]: struct.RankIndex.html
+    pub fn select(&self, mut k: usize) -> Option<Id> {
+        if k >= self.len {
+            return None;
+        }
+        for (word, block) in self.blocks.iter().enumerate() {
+            let ones = block.count_ones() as usize;
+            if k < ones {
+                return Some(word * T::BITS + select_bit(block, k));
+            }
+            k -= ones;
+        }
+        unreachable!()
+    }
+
     #[inline]
     /// Remove all elements that don't satisfy the predicate.
     pub fn retain<F: FnMut(Id) -> bool>(&mut self, mut pred: F) {
@@ -224,9 +547,9 @@ impl IdSet {
         for word in self.blocks.iter_mut() {
             let mut block = *word;
 
-            while block != 0 {
+            while block != T::ZERO {
                 let id = idx + block.trailing_zeros() as usize;
-                let mask = block - 1;
+                let mask = block.wrapping_sub(T::ONE);
 
                 if !pred(id) {
                     self.len -= 1;
@@ -235,57 +558,95 @@ impl IdSet {
                 block &= mask;
             }
 
-            idx += BITS;
+            idx += T::BITS;
         }
     }
 
     #[inline]
     /// Returns the underlying blocks as a slice.
-    pub fn as_blocks(&self) -> &[Block] {
+    pub fn as_blocks(&self) -> &[T] {
         &self.blocks
     }
 
+    #[inline]
+    /// Consumes the set and returns the underlying blocks. If the set is stored on the heap its
+    /// allocation is reused.
+    pub fn into_vec(self) -> Vec<T> {
+        self.blocks.into_vec()
+    }
+
     #[inline]
     /// An iterator over all elements in increasing order.
-    pub fn iter(&self) -> Iter {
+    pub fn iter(&self) -> Iter<'_, T> {
         Iter {
             inner: IdIter::new(self.blocks.iter()),
             len: self.len,
         }
     }
 
+    /// An iterator over all elements within the given range in increasing order.
+    pub fn range<R: RangeBounds<Id>>(&self, range: R) -> Range<'_, T> {
+        let (start, end) = range_bounds(&range);
+        let end = cmp::min(end.unwrap_or(usize::MAX), self.blocks.len() * T::BITS);
+        if start >= end {
+            return Range { inner: IdIter::from_parts(T::ZERO, 0, Blocks::new(&[]), T::ZERO) };
+        }
+
+        let (first, last) = (start / T::BITS, (end - 1) / T::BITS);
+        let lo = !low_mask::<T>(start % T::BITS);
+        let hi = T::MAX >> (T::BITS - 1 - (end - 1) % T::BITS);
+        let inner = if first == last {
+            IdIter::from_parts(self.blocks[first] & lo & hi,
+                               first * T::BITS,
+                               Blocks::new(&[]),
+                               T::ZERO)
+        } else {
+            IdIter::from_parts(self.blocks[first] & lo,
+                               first * T::BITS,
+                               Blocks::new(&self.blocks[first + 1..last]),
+                               self.blocks[last] & hi)
+        };
+        Range { inner }
+    }
+
+    #[inline]
+    /// An iterator over the maximal runs of consecutive elements in increasing order.
+    pub fn ranges(&self) -> Ranges<Blocks<'_, T>> {
+        Ranges::new(self.blocks.iter())
+    }
+
     #[inline]
     /// Returns an iterator over the blocks of the underlying representation.
-    pub fn blocks(&self) -> Blocks {
+    pub fn blocks(&self) -> Blocks<'_, T> {
         self.blocks.iter()
     }
 
     #[inline]
     /// Returns a consuming iterator over the blocks of the underlying representation.
-    pub fn into_blocks(self) -> IntoBlocks {
+    pub fn into_blocks(self) -> IntoBlocks<T, N> {
         self.blocks.into_iter()
     }
 
     #[inline]
     /// Takes the union of the set with another. Equivalent to `self | other`.
-    pub fn union<I>(&self, other: I) -> BlockIter<Union<Blocks, I::Blocks>>
-        where I: IntoBlockIterator
+    pub fn union<I>(&self, other: I) -> BlockIter<Union<Blocks<'_, T>, I::Blocks>>
+        where I: IntoBlockIterator<Block = T>
     {
         self | other
     }
 
     #[inline]
     /// Takes the intersection of the set with another. Equivalent to `self & other`.
-    pub fn intersection<I>(&self, other: I) -> BlockIter<Intersection<Blocks, I::Blocks>>
-        where I: IntoBlockIterator
+    pub fn intersection<I>(&self, other: I) -> BlockIter<Intersection<Blocks<'_, T>, I::Blocks>>
+        where I: IntoBlockIterator<Block = T>
     {
         self & other
     }
 
     #[inline]
     /// Takes the difference of the set with another. Equivalent to `self - other`.
-    pub fn difference<I>(&self, other: I) -> BlockIter<Difference<Blocks, I::Blocks>>
-        where I: IntoBlockIterator
+    pub fn difference<I>(&self, other: I) -> BlockIter<Difference<Blocks<'_, T>, I::Blocks>>
+        where I: IntoBlockIterator<Block = T>
     {
         self - other
     }
@@ -294,32 +655,32 @@ impl IdSet {
     /// Takes the symmetric difference of the set with another. Equivalent to `self ^ other`.
     pub fn symmetric_difference<I>(&self,
                                    other: I)
-                                   -> BlockIter<SymmetricDifference<Blocks, I::Blocks>>
-        where I: IntoBlockIterator
+                                   -> BlockIter<SymmetricDifference<Blocks<'_, T>, I::Blocks>>
+        where I: IntoBlockIterator<Block = T>
     {
         self ^ other
     }
 
     #[inline]
     /// Consumes the set and takes the union with another.
-    pub fn into_union<I>(self, other: I) -> BlockIter<Union<IntoBlocks, I::Blocks>>
-        where I: IntoBlockIterator
+    pub fn into_union<I>(self, other: I) -> BlockIter<Union<IntoBlocks<T, N>, I::Blocks>>
+        where I: IntoBlockIterator<Block = T>
     {
         self | other
     }
 
     #[inline]
     /// Consumes the set and takes the intersection with another.
-    pub fn into_intersection<I>(self, other: I) -> BlockIter<Intersection<IntoBlocks, I::Blocks>>
-        where I: IntoBlockIterator
+    pub fn into_intersection<I>(self, other: I) -> BlockIter<Intersection<IntoBlocks<T, N>, I::Blocks>>
+        where I: IntoBlockIterator<Block = T>
     {
         self & other
     }
 
     #[inline]
     /// Consumes the set and takes the difference with another.
-    pub fn into_difference<I>(self, other: I) -> BlockIter<Difference<IntoBlocks, I::Blocks>>
-        where I: IntoBlockIterator
+    pub fn into_difference<I>(self, other: I) -> BlockIter<Difference<IntoBlocks<T, N>, I::Blocks>>
+        where I: IntoBlockIterator<Block = T>
     {
         self - other
     }
@@ -328,8 +689,8 @@ impl IdSet {
     /// Consumes the set and takes the symmetric difference with another.
     pub fn into_symmetric_difference<I>(self,
                                         other: I)
-                                        -> BlockIter<SymmetricDifference<IntoBlocks, I::Blocks>>
-        where I: IntoBlockIterator
+                                        -> BlockIter<SymmetricDifference<IntoBlocks<T, N>, I::Blocks>>
+        where I: IntoBlockIterator<Block = T>
     {
         self ^ other
     }
@@ -337,7 +698,7 @@ impl IdSet {
     #[inline]
     /// Take the union of the set inplace with another set. Equivalent to `*self |= other`.
     pub fn inplace_union<I>(&mut self, other: I)
-        where I: IntoBlockIterator
+        where I: IntoBlockIterator<Block = T>
     {
         *self |= other
     }
@@ -345,7 +706,7 @@ impl IdSet {
     #[inline]
     /// Take the intersection of the set inplace with another set. Equivalent to `*self &= other`.
     pub fn inplace_intersection<I>(&mut self, other: I)
-        where I: IntoBlockIterator
+        where I: IntoBlockIterator<Block = T>
     {
         *self &= other
     }
@@ -353,7 +714,7 @@ impl IdSet {
     #[inline]
     /// Take the difference of the set inplace with another set. Equivalent to `*self -= other`.
     pub fn inplace_difference<I>(&mut self, other: I)
-        where I: IntoBlockIterator
+        where I: IntoBlockIterator<Block = T>
     {
         *self -= other
     }
@@ -362,32 +723,60 @@ impl IdSet {
     /// Take the symmetric difference of the set inplace with another set. Equivalent to
     /// `*self ^= other`.
     pub fn inplace_symmetric_difference<I>(&mut self, other: I)
-        where I: IntoBlockIterator
+        where I: IntoBlockIterator<Block = T>
     {
         *self ^= other
     }
 
+    #[inline]
+    /// Returns the number of elements in the intersection of the set with another, without
+    /// iterating over the elements.
+    pub fn intersection_len<const M: usize>(&self, other: &IdSet<T, M>) -> usize {
+        intersection_count(self.as_blocks(), other.as_blocks())
+    }
+
+    #[inline]
+    /// Returns the number of elements in the union of the set with another, without iterating over
+    /// the elements.
+    pub fn union_len<const M: usize>(&self, other: &IdSet<T, M>) -> usize {
+        self.len + other.len - self.intersection_len(other)
+    }
+
+    #[inline]
+    /// Returns the number of elements in the difference of the set with another, without iterating
+    /// over the elements.
+    pub fn difference_len<const M: usize>(&self, other: &IdSet<T, M>) -> usize {
+        self.len - self.intersection_len(other)
+    }
+
+    #[inline]
+    /// Returns the number of elements in the symmetric difference of the set with another, without
+    /// iterating over the elements.
+    pub fn symmetric_difference_len<const M: usize>(&self, other: &IdSet<T, M>) -> usize {
+        self.len + other.len - 2 * self.intersection_len(other)
+    }
+
     #[inline]
     /// Returns true if the sets are disjoint.
-    pub fn is_disjoint(&self, other: &Self) -> bool {
+    pub fn is_disjoint<const M: usize>(&self, other: &IdSet<T, M>) -> bool {
         self.len().saturating_add(other.len()) < cmp::max(self.capacity(), other.capacity()) &&
         self.intersection(other).into_iter().count() == 0
     }
 
     #[inline]
     /// Returns true if self is a superset of other.
-    pub fn is_superset(&self, other: &Self) -> bool {
+    pub fn is_superset<const M: usize>(&self, other: &IdSet<T, M>) -> bool {
         !other.is_subset(self)
     }
 
     #[inline]
     /// Returns true if self is a subset of other.
-    pub fn is_subset(&self, other: &Self) -> bool {
+    pub fn is_subset<const M: usize>(&self, other: &IdSet<T, M>) -> bool {
         self.len() <= other.len() && self.difference(other).into_iter().count() == 0
     }
 }
 
-impl Clone for IdSet {
+impl<T: BitBlock, const N: usize> Clone for IdSet<T, N> {
     #[inline]
     fn clone(&self) -> Self {
         IdSet {
@@ -403,7 +792,7 @@ impl Clone for IdSet {
     }
 }
 
-impl fmt::Debug for IdSet {
+impl<T: BitBlock, const N: usize> fmt::Debug for IdSet<T, N> {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         write!(f, "{{")?;
         let mut iter = self.iter();
@@ -417,17 +806,17 @@ impl fmt::Debug for IdSet {
     }
 }
 
-impl Default for IdSet {
+impl<T: BitBlock, const N: usize> Default for IdSet<T, N> {
     #[inline]
     fn default() -> Self {
-        IdSet::new()
+        IdSet::new_general()
     }
 }
 
-impl Eq for IdSet {}
+impl<T: BitBlock, const N: usize> Eq for IdSet<T, N> {}
 
-impl PartialEq for IdSet {
-    fn eq(&self, other: &Self) -> bool {
+impl<T: BitBlock, const N: usize, const M: usize> PartialEq<IdSet<T, M>> for IdSet<T, N> {
+    fn eq(&self, other: &IdSet<T, M>) -> bool {
         if self.len != other.len {
             return false;
         }
@@ -440,14 +829,34 @@ impl PartialEq for IdSet {
                     }
                 }
                 (None, None) => return true,
-                (Some(l), None) => return l == 0 && lhs.all(|block| block == 0),
-                (None, Some(r)) => return r == 0 && rhs.all(|block| block == 0),
+                (Some(l), None) => return l == T::ZERO && lhs.all(|block| block == T::ZERO),
+                (None, Some(r)) => return r == T::ZERO && rhs.all(|block| block == T::ZERO),
             }
         }
     }
 }
 
-impl Extend<Id> for IdSet {
+impl<T: BitBlock, const N: usize> From<Vec<T>> for IdSet<T, N> {
+    #[inline]
+    /// Creates a `IdSet` from the blocks of its underlying representation, reusing the vector's
+    /// allocation.
+    fn from(blocks: Vec<T>) -> Self {
+        let len = count_ones(&blocks);
+        IdSet {
+            blocks: BlockStore::Heap(blocks),
+            len,
+        }
+    }
+}
+
+impl<T: BitBlock, const N: usize> From<IdSet<T, N>> for Vec<T> {
+    #[inline]
+    fn from(set: IdSet<T, N>) -> Self {
+        set.into_vec()
+    }
+}
+
+impl<T: BitBlock, const N: usize> Extend<Id> for IdSet<T, N> {
     #[inline]
     fn extend<I: IntoIterator<Item = Id>>(&mut self, iter: I) {
         for id in iter {
@@ -456,10 +865,10 @@ impl Extend<Id> for IdSet {
     }
 }
 
-impl FromIterator<Id> for IdSet {
+impl<T: BitBlock, const N: usize> FromIterator<Id> for IdSet<T, N> {
     #[inline]
     fn from_iter<I: IntoIterator<Item = Id>>(iter: I) -> Self {
-        let mut set = IdSet::new();
+        let mut set = IdSet::new_general();
         for id in iter {
             set.insert(id);
         }
@@ -467,9 +876,9 @@ impl FromIterator<Id> for IdSet {
     }
 }
 
-impl<'a> IntoIterator for &'a IdSet {
+impl<'a, T: BitBlock, const N: usize> IntoIterator for &'a IdSet<T, N> {
     type Item = Id;
-    type IntoIter = Iter<'a>;
+    type IntoIter = Iter<'a, T>;
 
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
@@ -479,12 +888,12 @@ impl<'a> IntoIterator for &'a IdSet {
 
 #[derive(Clone, Debug)]
 /// An iterator over all elements in increasing order.
-pub struct Iter<'a> {
-    inner: IdIter<Blocks<'a>>,
+pub struct Iter<'a, T: BitBlock = Block> {
+    inner: IdIter<Blocks<'a, T>>,
     len: usize,
 }
 
-impl<'a> Iterator for Iter<'a> {
+impl<'a, T: BitBlock> Iterator for Iter<'a, T> {
     type Item = Id;
 
     #[inline]
@@ -502,16 +911,54 @@ impl<'a> Iterator for Iter<'a> {
     }
 }
 
-impl<'a> ExactSizeIterator for Iter<'a> {
+impl<'a, T: BitBlock> DoubleEndedIterator for Iter<'a, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let id = self.inner.next_back();
+        if id.is_some() {
+            self.len -= 1;
+        }
+        id
+    }
+}
+
+impl<'a, T: BitBlock> ExactSizeIterator for Iter<'a, T> {
     #[inline]
     fn len(&self) -> usize {
         self.len
     }
 }
 
-impl IntoIterator for IdSet {
+#[derive(Clone, Debug)]
+/// An iterator over the elements of a set within a range in increasing order.
+pub struct Range<'a, T: BitBlock = Block> {
+    inner: IdIter<Blocks<'a, T>>,
+}
+
+impl<'a, T: BitBlock> Iterator for Range<'a, T> {
     type Item = Id;
-    type IntoIter = IntoIter;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<'a, T: BitBlock> DoubleEndedIterator for Range<'a, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back()
+    }
+}
+
+impl<T: BitBlock, const N: usize> IntoIterator for IdSet<T, N> {
+    type Item = Id;
+    type IntoIter = IntoIter<T, N>;
 
     #[inline]
     fn into_iter(self) -> Self::IntoIter {
@@ -525,12 +972,12 @@ impl IntoIterator for IdSet {
 
 #[derive(Clone, Debug)]
 /// A consuming iterator over all elements in increasing order.
-pub struct IntoIter {
-    inner: IdIter<IntoBlocks>,
+pub struct IntoIter<T: BitBlock = Block, const N: usize = DEFAULT_INLINE> {
+    inner: IdIter<IntoBlocks<T, N>>,
     len: usize,
 }
 
-impl Iterator for IntoIter {
+impl<T: BitBlock, const N: usize> Iterator for IntoIter<T, N> {
     type Item = Id;
 
     #[inline]
@@ -549,7 +996,18 @@ impl Iterator for IntoIter {
     }
 }
 
-impl ExactSizeIterator for IntoIter {
+impl<T: BitBlock, const N: usize> DoubleEndedIterator for IntoIter<T, N> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let id = self.inner.next_back();
+        if id.is_some() {
+            self.len -= 1;
+        }
+        id
+    }
+}
+
+impl<T: BitBlock, const N: usize> ExactSizeIterator for IntoIter<T, N> {
     #[inline]
     fn len(&self) -> usize {
         self.len
@@ -558,48 +1016,151 @@ impl ExactSizeIterator for IntoIter {
 
 #[derive(Clone, Debug)]
 /// Transforms an iterator over blocks into an iterator over elements.
-pub struct IdIter<B> {
+pub struct IdIter<B: Iterator> {
     blocks: B,
-    word: Block,
+    // The block currently being consumed from the front, and the element of its first bit.
+    word: B::Item,
     idx: usize,
+    // The block currently being consumed from the back, and the element of its first bit.
+    back: B::Item,
+    back_idx: usize,
 }
 
 impl<B> IdIter<B>
-    where B: ExactSizeIterator<Item = Block>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
 {
     /// Creates a new iterator over elements of a block iterator.
     pub fn new<I: IntoBlockIterator<Blocks = B>>(iter: I) -> Self {
         let mut blocks = iter.into_block_iter().into_inner();
-        let word = blocks.next().unwrap_or(0);
+        let word = blocks.next().unwrap_or(B::Item::ZERO);
+        IdIter::from_parts(word, 0, blocks, B::Item::ZERO)
+    }
+
+    /// Creates an iterator over the front block `word`, whose first bit is the element `idx`, then
+    /// the blocks of `blocks`, then the back block `back`.
+    fn from_parts(word: B::Item, idx: usize, blocks: B, back: B::Item) -> Self {
+        let back_idx = idx + (blocks.len() + 1) * B::Item::BITS;
         IdIter {
             blocks,
             word,
-            idx: 0,
+            idx,
+            back,
+            back_idx,
         }
     }
 }
 
 impl<B> Iterator for IdIter<B>
-    where B: ExactSizeIterator<Item = Block>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
 {
     type Item = Id;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
-        while self.word == 0 {
+        while self.word == B::Item::ZERO {
             match self.blocks.next() {
                 Some(word) => self.word = word,
-                None => return None,
+                None => {
+                    return if self.back == B::Item::ZERO {
+                        None
+                    } else {
+                        Some(self.back_idx + pop_lsb(&mut self.back))
+                    };
+                }
             }
-            self.idx += BITS;
+            self.idx += B::Item::BITS;
         }
         Some(self.idx + pop_lsb(&mut self.word))
     }
 
     #[inline]
     fn size_hint(&self) -> (usize, Option<usize>) {
-        let ones = self.word.count_ones() as usize;
-        (ones, Some(self.blocks.len().saturating_mul(BITS).saturating_add(ones)))
+        let ones = (self.word.count_ones() + self.back.count_ones()) as usize;
+        (ones, Some(self.blocks.len().saturating_mul(B::Item::BITS).saturating_add(ones)))
+    }
+}
+
+impl<B> DoubleEndedIterator for IdIter<B>
+    where B: DoubleEndedIterator + ExactSizeIterator,
+          B::Item: BitBlock
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        while self.back == B::Item::ZERO {
+            match self.blocks.next_back() {
+                Some(back) => {
+                    self.back = back;
+                    self.back_idx = self.idx + (self.blocks.len() + 1) * B::Item::BITS;
+                }
+                None => {
+                    return if self.word == B::Item::ZERO {
+                        None
+                    } else {
+                        Some(self.idx + pop_msb(&mut self.word))
+                    };
+                }
+            }
+        }
+        Some(self.back_idx + pop_msb(&mut self.back))
+    }
+}
+
+#[derive(Clone, Debug)]
+/// An iterator over the maximal runs of consecutive elements of a block iterator.
+pub struct Ranges<B: Iterator> {
+    blocks: B,
+    // The unconsumed bits of the current block, and the element of its first bit.
+    word: B::Item,
+    idx: usize,
+}
+
+impl<B> Ranges<B>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
+{
+    fn new(mut blocks: B) -> Self {
+        let word = blocks.next().unwrap_or(B::Item::ZERO);
+        Ranges {
+            blocks,
+            word,
+            idx: 0,
+        }
+    }
+}
+
+impl<B> Iterator for Ranges<B>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
+{
+    type Item = ops::Range<Id>;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        while self.word == B::Item::ZERO {
+            self.word = self.blocks.next()?;
+            self.idx += B::Item::BITS;
+        }
+        let start = self.word.trailing_zeros() as usize;
+        let end = start + (!(self.word >> start)).trailing_zeros() as usize;
+        if end < B::Item::BITS {
+            self.word &= !low_mask::<B::Item>(end);
+            return Some(self.idx + start..self.idx + end);
+        }
+
+        // The run extends to the end of the block, so continue it through the following blocks.
+        let start = self.idx + start;
+        self.word = B::Item::ZERO;
+        for block in self.blocks.by_ref() {
+            self.idx += B::Item::BITS;
+            if block != B::Item::MAX {
+                let end = (!block).trailing_zeros() as usize;
+                self.word = block & !low_mask::<B::Item>(end);
+                return Some(start..self.idx + end);
+            }
+        }
+        Some(start..self.idx + B::Item::BITS)
     }
 }
 
@@ -618,7 +1179,8 @@ impl<B> BlockIter<B> {
 }
 
 impl<B> BlockIter<B>
-    where B: ExactSizeIterator<Item = Block>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
 {
     /// Creates new block iterator.
     pub fn new(inner: B) -> Self {
@@ -627,15 +1189,15 @@ impl<B> BlockIter<B>
 
     #[inline]
     /// Equivalent to `self.into_iter().collect()`.
-    pub fn collect<T>(self) -> T
-        where T: iter::FromIterator<Id>
+    pub fn collect<C>(self) -> C
+        where C: iter::FromIterator<Id>
     {
         self.into_iter().collect()
     }
 
     #[inline]
     /// Collects the iterator into an `IdSet`.
-    pub fn into_set(self) -> IdSet {
+    pub fn into_set(self) -> IdSet<B::Item> {
         let mut len = 0;
         let blocks = self.inner.inspect(|&block| len += block.count_ones() as usize).collect();
         IdSet {
@@ -643,10 +1205,22 @@ impl<B> BlockIter<B>
         }
     }
 
+    #[inline]
+    /// Returns the number of elements in the blocks, without iterating over the elements.
+    pub fn count_ones(self) -> usize {
+        self.inner.map(|block| block.count_ones() as usize).sum()
+    }
+
+    #[inline]
+    /// Returns an iterator over the maximal runs of consecutive elements in increasing order.
+    pub fn ranges(self) -> Ranges<B> {
+        Ranges::new(self.inner)
+    }
+
     #[inline]
     /// Takes the union of the blocks with another block iterator. Equivalent to `self | other`.
     pub fn union<I>(self, other: I) -> BlockIter<Union<B, I::Blocks>>
-        where I: IntoBlockIterator
+        where I: IntoBlockIterator<Block = B::Item>
     {
         self | other
     }
@@ -655,7 +1229,7 @@ impl<B> BlockIter<B>
     /// Takes the intersection of the blocks with another block iterator. Equivalent to
     /// `self & other`.
     pub fn intersection<I>(self, other: I) -> BlockIter<Intersection<B, I::Blocks>>
-        where I: IntoBlockIterator
+        where I: IntoBlockIterator<Block = B::Item>
     {
         self & other
     }
@@ -664,7 +1238,7 @@ impl<B> BlockIter<B>
     /// Takes the difference of the blocks with another block iterator. Equivalent to
     /// `self - other`.
     pub fn difference<I>(self, other: I) -> BlockIter<Difference<B, I::Blocks>>
-        where I: IntoBlockIterator
+        where I: IntoBlockIterator<Block = B::Item>
     {
         self - other
     }
@@ -673,14 +1247,15 @@ impl<B> BlockIter<B>
     /// Takes the symmetric difference of the blocks with another block iterator. Equivalent to
     /// `self ^ other`.
     pub fn symmetric_difference<I>(self, other: I) -> BlockIter<SymmetricDifference<B, I::Blocks>>
-        where I: IntoBlockIterator
+        where I: IntoBlockIterator<Block = B::Item>
     {
         self ^ other
     }
 }
 
 impl<B> IntoIterator for BlockIter<B>
-    where B: ExactSizeIterator<Item = Block>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
 {
     type Item = Id;
     type IntoIter = IdIter<B>;
@@ -690,18 +1265,73 @@ impl<B> IntoIterator for BlockIter<B>
     }
 }
 
+/// A boxed iterator over blocks, whose type does not depend on how it was built.
+pub type DynBlocks<'a, T = Block> = Box<dyn ExactSizeIterator<Item = T> + 'a>;
+
+/// A block iterator over a boxed iterator, created with
+/// [`IntoBlockIterator::into_dyn`](trait.IntoBlockIterator.html#method.into_dyn).
+///
+/// Since the type of a combination of sets depends on the combination, it cannot be named for
+/// expressions built at runtime. Boxing each intermediate result gives them all the same type.
+///
+/// # Examples
+///
+/// ```
+/// # use id_set::{DynBlockIter, IdSet, IntoBlockIterator};
+/// #
+/// let sets: Vec<IdSet> = (1..4).map(|n| (0..20).filter(|m| m % n == 0).collect()).collect();
+///
+/// // Computes sets[0] - (sets[1] | sets[2]).
+/// let excluded = sets[1..]
+///     .iter()
+///     .fold(DynBlockIter::empty(), |acc, set| (acc | set).into_dyn());
+/// let expr = (&sets[0] - excluded).into_dyn();
+/// assert_eq!(expr.collect::<Vec<_>>(), [1, 5, 7, 11, 13, 17, 19]);
+/// ```
+pub type DynBlockIter<'a, T = Block> = BlockIter<DynBlocks<'a, T>>;
+
+impl<'a, T: BitBlock> BlockIter<DynBlocks<'a, T>> {
+    #[inline]
+    /// Creates a boxed block iterator with no blocks.
+    pub fn empty() -> Self {
+        BlockIter::new(Box::new(iter::empty()))
+    }
+}
+
 /// Conversion into an iterator over blocks.
 pub trait IntoBlockIterator {
+    /// The block type.
+    type Block: BitBlock;
+
     /// The raw iterator type.
-    type Blocks: ExactSizeIterator<Item = Block>;
+    type Blocks: ExactSizeIterator<Item = Self::Block>;
 
     /// Creates a block iterator.
     fn into_block_iter(self) -> BlockIter<Self::Blocks>;
+
+    #[inline]
+    /// Returns the blocks as a contiguous slice, if they are stored in one. This lets inplace set
+    /// operations process many blocks at a time. The default implementation returns `None`.
+    fn block_slice(&self) -> Option<&[Self::Block]> {
+        None
+    }
+
+    #[inline]
+    /// Creates a block iterator with a boxed inner iterator, erasing its type. This allows
+    /// combinations of sets to be built at runtime.
+    fn into_dyn<'a>(self) -> DynBlockIter<'a, Self::Block>
+        where Self: Sized,
+              Self::Blocks: 'a
+    {
+        BlockIter::new(Box::new(self.into_block_iter().into_inner()))
+    }
 }
 
 impl<B> IntoBlockIterator for B
-    where B: ExactSizeIterator<Item = Block>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
 {
+    type Block = B::Item;
     type Blocks = B;
 
     fn into_block_iter(self) -> BlockIter<Self::Blocks> {
@@ -710,8 +1340,10 @@ impl<B> IntoBlockIterator for B
 }
 
 impl<B> IntoBlockIterator for BlockIter<B>
-    where B: ExactSizeIterator<Item = Block>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
 {
+    type Block = B::Item;
     type Blocks = B;
 
     #[inline]
@@ -720,27 +1352,40 @@ impl<B> IntoBlockIterator for BlockIter<B>
     }
 }
 
-impl<'a> IntoBlockIterator for &'a IdSet {
-    type Blocks = Blocks<'a>;
+impl<'a, T: BitBlock, const N: usize> IntoBlockIterator for &'a IdSet<T, N> {
+    type Block = T;
+    type Blocks = Blocks<'a, T>;
 
     #[inline]
     fn into_block_iter(self) -> BlockIter<Self::Blocks> {
         self.blocks().into_block_iter()
     }
+
+    #[inline]
+    fn block_slice(&self) -> Option<&[T]> {
+        Some(self.as_blocks())
+    }
 }
 
-impl IntoBlockIterator for IdSet {
-    type Blocks = IntoBlocks;
+impl<T: BitBlock, const N: usize> IntoBlockIterator for IdSet<T, N> {
+    type Block = T;
+    type Blocks = IntoBlocks<T, N>;
 
     #[inline]
     fn into_block_iter(self) -> BlockIter<Self::Blocks> {
         self.into_blocks().into_block_iter()
     }
+
+    #[inline]
+    fn block_slice(&self) -> Option<&[T]> {
+        Some(self.as_blocks())
+    }
 }
 
 impl<B, I> ops::BitAnd<I> for BlockIter<B>
-    where B: ExactSizeIterator<Item = Block>,
-          I: IntoBlockIterator
+    where B: ExactSizeIterator,
+          B::Item: BitBlock,
+          I: IntoBlockIterator<Block = B::Item>
 {
     type Output = BlockIter<Intersection<B, I::Blocks>>;
 
@@ -757,8 +1402,9 @@ impl<B, I> ops::BitAnd<I> for BlockIter<B>
 }
 
 impl<B, I> ops::BitOr<I> for BlockIter<B>
-    where B: ExactSizeIterator<Item = Block>,
-          I: IntoBlockIterator
+    where B: ExactSizeIterator,
+          B::Item: BitBlock,
+          I: IntoBlockIterator<Block = B::Item>
 {
     type Output = BlockIter<Union<B, I::Blocks>>;
 
@@ -775,8 +1421,9 @@ impl<B, I> ops::BitOr<I> for BlockIter<B>
 }
 
 impl<B, I> ops::BitXor<I> for BlockIter<B>
-    where B: ExactSizeIterator<Item = Block>,
-          I: IntoBlockIterator
+    where B: ExactSizeIterator,
+          B::Item: BitBlock,
+          I: IntoBlockIterator<Block = B::Item>
 {
     type Output = BlockIter<SymmetricDifference<B, I::Blocks>>;
 
@@ -793,8 +1440,9 @@ impl<B, I> ops::BitXor<I> for BlockIter<B>
 }
 
 impl<B, I> ops::Sub<I> for BlockIter<B>
-    where B: ExactSizeIterator<Item = Block>,
-          I: IntoBlockIterator
+    where B: ExactSizeIterator,
+          B::Item: BitBlock,
+          I: IntoBlockIterator<Block = B::Item>
 {
     type Output = BlockIter<Difference<B, I::Blocks>>;
 
@@ -810,10 +1458,11 @@ impl<B, I> ops::Sub<I> for BlockIter<B>
     }
 }
 
-impl<'a, I> ops::BitAnd<I> for &'a IdSet
-    where I: IntoBlockIterator
+impl<'a, T, I, const N: usize> ops::BitAnd<I> for &'a IdSet<T, N>
+    where T: BitBlock,
+          I: IntoBlockIterator<Block = T>
 {
-    type Output = BlockIter<Intersection<Blocks<'a>, I::Blocks>>;
+    type Output = BlockIter<Intersection<Blocks<'a, T>, I::Blocks>>;
 
     #[inline]
     /// Takes the intersection of two objects.
@@ -822,10 +1471,11 @@ impl<'a, I> ops::BitAnd<I> for &'a IdSet
     }
 }
 
-impl<'a, I> ops::BitOr<I> for &'a IdSet
-    where I: IntoBlockIterator
+impl<'a, T, I, const N: usize> ops::BitOr<I> for &'a IdSet<T, N>
+    where T: BitBlock,
+          I: IntoBlockIterator<Block = T>
 {
-    type Output = BlockIter<Union<Blocks<'a>, I::Blocks>>;
+    type Output = BlockIter<Union<Blocks<'a, T>, I::Blocks>>;
 
     #[inline]
     /// Takes the union of two objects.
@@ -834,10 +1484,11 @@ impl<'a, I> ops::BitOr<I> for &'a IdSet
     }
 }
 
-impl<'a, I> ops::BitXor<I> for &'a IdSet
-    where I: IntoBlockIterator
+impl<'a, T, I, const N: usize> ops::BitXor<I> for &'a IdSet<T, N>
+    where T: BitBlock,
+          I: IntoBlockIterator<Block = T>
 {
-    type Output = BlockIter<SymmetricDifference<Blocks<'a>, I::Blocks>>;
+    type Output = BlockIter<SymmetricDifference<Blocks<'a, T>, I::Blocks>>;
 
     #[inline]
     /// Takes the symmetric difference of two objects.
@@ -846,10 +1497,11 @@ impl<'a, I> ops::BitXor<I> for &'a IdSet
     }
 }
 
-impl<'a, I> ops::Sub<I> for &'a IdSet
-    where I: IntoBlockIterator
+impl<'a, T, I, const N: usize> ops::Sub<I> for &'a IdSet<T, N>
+    where T: BitBlock,
+          I: IntoBlockIterator<Block = T>
 {
-    type Output = BlockIter<Difference<Blocks<'a>, I::Blocks>>;
+    type Output = BlockIter<Difference<Blocks<'a, T>, I::Blocks>>;
 
     #[inline]
     /// Takes the difference of two objects.
@@ -858,10 +1510,11 @@ impl<'a, I> ops::Sub<I> for &'a IdSet
     }
 }
 
-impl<I> ops::BitAnd<I> for IdSet
-    where I: IntoBlockIterator
+impl<T, I, const N: usize> ops::BitAnd<I> for IdSet<T, N>
+    where T: BitBlock,
+          I: IntoBlockIterator<Block = T>
 {
-    type Output = BlockIter<Intersection<IntoBlocks, I::Blocks>>;
+    type Output = BlockIter<Intersection<IntoBlocks<T, N>, I::Blocks>>;
 
     #[inline]
     /// Takes the intersection of two objects.
@@ -870,10 +1523,11 @@ impl<I> ops::BitAnd<I> for IdSet
     }
 }
 
-impl<I> ops::BitOr<I> for IdSet
-    where I: IntoBlockIterator
+impl<T, I, const N: usize> ops::BitOr<I> for IdSet<T, N>
+    where T: BitBlock,
+          I: IntoBlockIterator<Block = T>
 {
-    type Output = BlockIter<Union<IntoBlocks, I::Blocks>>;
+    type Output = BlockIter<Union<IntoBlocks<T, N>, I::Blocks>>;
 
     #[inline]
     /// Takes the union of two objects.
@@ -882,10 +1536,11 @@ impl<I> ops::BitOr<I> for IdSet
     }
 }
 
-impl<I> ops::BitXor<I> for IdSet
-    where I: IntoBlockIterator
+impl<T, I, const N: usize> ops::BitXor<I> for IdSet<T, N>
+    where T: BitBlock,
+          I: IntoBlockIterator<Block = T>
 {
-    type Output = BlockIter<SymmetricDifference<IntoBlocks, I::Blocks>>;
+    type Output = BlockIter<SymmetricDifference<IntoBlocks<T, N>, I::Blocks>>;
 
     #[inline]
     /// Takes the symmetric difference of two objects.
@@ -894,10 +1549,11 @@ impl<I> ops::BitXor<I> for IdSet
     }
 }
 
-impl<I> ops::Sub<I> for IdSet
-    where I: IntoBlockIterator
+impl<T, I, const N: usize> ops::Sub<I> for IdSet<T, N>
+    where T: BitBlock,
+          I: IntoBlockIterator<Block = T>
 {
-    type Output = BlockIter<Difference<IntoBlocks, I::Blocks>>;
+    type Output = BlockIter<Difference<IntoBlocks<T, N>, I::Blocks>>;
 
     #[inline]
     /// Takes the difference of two objects.
@@ -906,12 +1562,21 @@ impl<I> ops::Sub<I> for IdSet
     }
 }
 
-impl<I> ops::BitAndAssign<I> for IdSet
-    where I: IntoBlockIterator
+impl<T, I, const N: usize> ops::BitAndAssign<I> for IdSet<T, N>
+    where T: BitBlock,
+          I: IntoBlockIterator<Block = T>
 {
     #[inline]
     /// Takes the inplace intersection of the set with another.
     fn bitand_assign(&mut self, other: I) {
+        if let Some(rhs) = other.block_slice() {
+            if rhs.len() < self.blocks.len() {
+                self.blocks.drain(rhs.len()).for_each(drop);
+            }
+            let n = cmp::min(self.blocks.len(), rhs.len());
+            self.len = simd::apply(&mut self.blocks[..n], &rhs[..n], simd::Op::And);
+            return;
+        }
         let blocks = other.into_block_iter().into_inner();
         if blocks.len() < self.blocks.len() {
             for block in self.blocks.drain(blocks.len()) {
@@ -925,12 +1590,22 @@ impl<I> ops::BitAndAssign<I> for IdSet
     }
 }
 
-impl<I> ops::BitOrAssign<I> for IdSet
-    where I: IntoBlockIterator
+impl<T, I, const N: usize> ops::BitOrAssign<I> for IdSet<T, N>
+    where T: BitBlock,
+          I: IntoBlockIterator<Block = T>
 {
     #[inline]
     /// Takes the inplace union of the set with another.
     fn bitor_assign(&mut self, other: I) {
+        if let Some(rhs) = other.block_slice() {
+            let n = cmp::min(self.blocks.len(), rhs.len());
+            if rhs.len() > n {
+                self.blocks.extend(rhs[n..].iter().cloned());
+            }
+            self.len = simd::apply(&mut self.blocks[..n], &rhs[..n], simd::Op::Or) +
+                       count_ones(&self.blocks[n..]);
+            return;
+        }
         let mut blocks = other.into_block_iter().into_inner();
         for lblock in self.blocks.iter_mut() {
             if let Some(rblock) = blocks.next() {
@@ -946,12 +1621,22 @@ impl<I> ops::BitOrAssign<I> for IdSet
     }
 }
 
-impl<I> ops::BitXorAssign<I> for IdSet
-    where I: IntoBlockIterator
+impl<T, I, const N: usize> ops::BitXorAssign<I> for IdSet<T, N>
+    where T: BitBlock,
+          I: IntoBlockIterator<Block = T>
 {
     #[inline]
     /// Takes the inplace symmetric difference of the set with another.
     fn bitxor_assign(&mut self, other: I) {
+        if let Some(rhs) = other.block_slice() {
+            let n = cmp::min(self.blocks.len(), rhs.len());
+            if rhs.len() > n {
+                self.blocks.extend(rhs[n..].iter().cloned());
+            }
+            self.len = simd::apply(&mut self.blocks[..n], &rhs[..n], simd::Op::Xor) +
+                       count_ones(&self.blocks[n..]);
+            return;
+        }
         let mut blocks = other.into_block_iter().into_inner();
         for lblock in self.blocks.iter_mut() {
             if let Some(rblock) = blocks.next() {
@@ -968,12 +1653,19 @@ impl<I> ops::BitXorAssign<I> for IdSet
     }
 }
 
-impl<I> ops::SubAssign<I> for IdSet
-    where I: IntoBlockIterator
+impl<T, I, const N: usize> ops::SubAssign<I> for IdSet<T, N>
+    where T: BitBlock,
+          I: IntoBlockIterator<Block = T>
 {
     #[inline]
     /// Takes the inplace difference of the set with another.
     fn sub_assign(&mut self, other: I) {
+        if let Some(rhs) = other.block_slice() {
+            let n = cmp::min(self.blocks.len(), rhs.len());
+            self.len = simd::apply(&mut self.blocks[..n], &rhs[..n], simd::Op::AndNot) +
+                       count_ones(&self.blocks[n..]);
+            return;
+        }
         for (lblock, rblock) in self.blocks
                 .iter_mut()
                 .zip(other.into_block_iter().into_inner()) {
@@ -991,10 +1683,11 @@ pub struct Intersection<L, R> {
 }
 
 impl<L, R> Iterator for Intersection<L, R>
-    where L: ExactSizeIterator<Item = Block>,
-          R: ExactSizeIterator<Item = Block>
+    where L: ExactSizeIterator,
+          L::Item: BitBlock,
+          R: ExactSizeIterator<Item = L::Item>
 {
-    type Item = Block;
+    type Item = L::Item;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -1012,9 +1705,32 @@ impl<L, R> Iterator for Intersection<L, R>
     }
 }
 
+impl<L, R> DoubleEndedIterator for Intersection<L, R>
+    where L: DoubleEndedIterator + ExactSizeIterator,
+          L::Item: BitBlock,
+          R: DoubleEndedIterator + ExactSizeIterator<Item = L::Item>
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (llen, rlen) = (self.left.len(), self.right.len());
+        for _ in rlen..llen {
+            self.left.next_back();
+        }
+        for _ in llen..rlen {
+            self.right.next_back();
+        }
+        if let (Some(l), Some(r)) = (self.left.next_back(), self.right.next_back()) {
+            Some(l & r)
+        } else {
+            None
+        }
+    }
+}
+
 impl<L, R> ExactSizeIterator for Intersection<L, R>
-    where L: ExactSizeIterator<Item = Block>,
-          R: ExactSizeIterator<Item = Block>
+    where L: ExactSizeIterator,
+          L::Item: BitBlock,
+          R: ExactSizeIterator<Item = L::Item>
 {
     #[inline]
     fn len(&self) -> usize {
@@ -1030,10 +1746,11 @@ pub struct Union<L, R> {
 }
 
 impl<L, R> Iterator for Union<L, R>
-    where L: ExactSizeIterator<Item = Block>,
-          R: ExactSizeIterator<Item = Block>
+    where L: ExactSizeIterator,
+          L::Item: BitBlock,
+          R: ExactSizeIterator<Item = L::Item>
 {
-    type Item = Block;
+    type Item = L::Item;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -1052,9 +1769,31 @@ impl<L, R> Iterator for Union<L, R>
     }
 }
 
+impl<L, R> DoubleEndedIterator for Union<L, R>
+    where L: DoubleEndedIterator + ExactSizeIterator,
+          L::Item: BitBlock,
+          R: DoubleEndedIterator + ExactSizeIterator<Item = L::Item>
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self.left.len().cmp(&self.right.len()) {
+            cmp::Ordering::Greater => self.left.next_back(),
+            cmp::Ordering::Less => self.right.next_back(),
+            cmp::Ordering::Equal => {
+                if let (Some(l), Some(r)) = (self.left.next_back(), self.right.next_back()) {
+                    Some(l | r)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
 impl<L, R> ExactSizeIterator for Union<L, R>
-    where L: ExactSizeIterator<Item = Block>,
-          R: ExactSizeIterator<Item = Block>
+    where L: ExactSizeIterator,
+          L::Item: BitBlock,
+          R: ExactSizeIterator<Item = L::Item>
 {
     #[inline]
     fn len(&self) -> usize {
@@ -1070,10 +1809,11 @@ pub struct SymmetricDifference<L, R> {
 }
 
 impl<L, R> Iterator for SymmetricDifference<L, R>
-    where L: ExactSizeIterator<Item = Block>,
-          R: ExactSizeIterator<Item = Block>
+    where L: ExactSizeIterator,
+          L::Item: BitBlock,
+          R: ExactSizeIterator<Item = L::Item>
 {
-    type Item = Block;
+    type Item = L::Item;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
@@ -1092,9 +1832,31 @@ impl<L, R> Iterator for SymmetricDifference<L, R>
     }
 }
 
+impl<L, R> DoubleEndedIterator for SymmetricDifference<L, R>
+    where L: DoubleEndedIterator + ExactSizeIterator,
+          L::Item: BitBlock,
+          R: DoubleEndedIterator + ExactSizeIterator<Item = L::Item>
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        match self.left.len().cmp(&self.right.len()) {
+            cmp::Ordering::Greater => self.left.next_back(),
+            cmp::Ordering::Less => self.right.next_back(),
+            cmp::Ordering::Equal => {
+                if let (Some(l), Some(r)) = (self.left.next_back(), self.right.next_back()) {
+                    Some(l ^ r)
+                } else {
+                    None
+                }
+            }
+        }
+    }
+}
+
 impl<L, R> ExactSizeIterator for SymmetricDifference<L, R>
-    where L: ExactSizeIterator<Item = Block>,
-          R: ExactSizeIterator<Item = Block>
+    where L: ExactSizeIterator,
+          L::Item: BitBlock,
+          R: ExactSizeIterator<Item = L::Item>
 {
     #[inline]
     fn len(&self) -> usize {
@@ -1110,16 +1872,17 @@ pub struct Difference<L, R> {
 }
 
 impl<L, R> Iterator for Difference<L, R>
-    where L: ExactSizeIterator<Item = Block>,
-          R: ExactSizeIterator<Item = Block>
+    where L: ExactSizeIterator,
+          L::Item: BitBlock,
+          R: ExactSizeIterator<Item = L::Item>
 {
-    type Item = Block;
+    type Item = L::Item;
 
     #[inline]
     fn next(&mut self) -> Option<Self::Item> {
         self.left
             .next()
-            .map(|l| l & !self.right.next().unwrap_or(0))
+            .map(|l| l & !self.right.next().unwrap_or(L::Item::ZERO))
     }
 
     #[inline]
@@ -1129,9 +1892,30 @@ impl<L, R> Iterator for Difference<L, R>
     }
 }
 
+impl<L, R> DoubleEndedIterator for Difference<L, R>
+    where L: DoubleEndedIterator + ExactSizeIterator,
+          L::Item: BitBlock,
+          R: DoubleEndedIterator + ExactSizeIterator<Item = L::Item>
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let (llen, rlen) = (self.left.len(), self.right.len());
+        for _ in llen..rlen {
+            self.right.next_back();
+        }
+        let l = self.left.next_back()?;
+        if llen > rlen {
+            Some(l)
+        } else {
+            Some(l & !self.right.next_back().unwrap_or(L::Item::ZERO))
+        }
+    }
+}
+
 impl<L, R> ExactSizeIterator for Difference<L, R>
-    where L: ExactSizeIterator<Item = Block>,
-          R: ExactSizeIterator<Item = Block>
+    where L: ExactSizeIterator,
+          L::Item: BitBlock,
+          R: ExactSizeIterator<Item = L::Item>
 {
     #[inline]
     fn len(&self) -> usize {
@@ -0,0 +1,633 @@
+use std::{fmt, mem, ops};
+use std::iter::FromIterator;
+use std::mem::MaybeUninit;
+
+use super::{BitBlock, Block, Id, IdSet};
+
+/// A map keyed by `usize` ids, storing values densely in a vector indexed by id.
+///
+/// The occupied ids are stored in an `IdSet`, which can be accessed with [`keys`](#method.keys)
+/// and combined with other sets. Values are only initialized for ids in the set, so unlike a
+/// `Vec<Option<V>>` no space is used for a discriminant.
+///
+/// # Examples
+///
+/// ```
+/// # use id_set::{IdMap, IdSet};
+/// #
+/// let mut names = IdMap::new();
+/// names.insert(3, "alice");
+/// names.insert(7, "bob");
+/// *names.entry(3).or_insert("carol") = "dave";
+/// assert_eq!(names.iter().collect::<Vec<_>>(), [(3, &"dave"), (7, &"bob")]);
+///
+/// let banned: IdSet = vec![7, 8].into_iter().collect();
+/// assert_eq!((names.keys() - &banned).collect::<Vec<_>>(), [3]);
+/// ```
+pub struct IdMap<V, T: BitBlock = Block> {
+    keys: IdSet<T>,
+    // The value for each id in `keys` is initialized. All other values are uninitialized.
+    values: Vec<MaybeUninit<V>>,
+}
+
+impl<V> IdMap<V> {
+    #[inline]
+    /// Creates an empty `IdMap`.
+    pub fn new() -> Self {
+        IdMap::new_general()
+    }
+
+    #[inline]
+    /// Creates an empty `IdMap` that can hold ids up to n before reallocating.
+    pub fn with_capacity(n: usize) -> Self {
+        IdMap::with_capacity_general(n)
+    }
+}
+
+impl<V, T: BitBlock> IdMap<V, T> {
+    #[inline]
+    /// Creates an empty `IdMap` with any block type.
+    pub fn new_general() -> Self {
+        IdMap {
+            keys: IdSet::new_general(),
+            values: Vec::new(),
+        }
+    }
+
+    #[inline]
+    /// Creates an empty `IdMap` with any block type that can hold ids up to n before reallocating.
+    pub fn with_capacity_general(n: usize) -> Self {
+        IdMap {
+            keys: IdSet::with_capacity_general(n),
+            values: Vec::with_capacity(n),
+        }
+    }
+
+    #[inline]
+    /// Returns the number of entries in the map.
+    pub fn len(&self) -> usize {
+        self.keys.len()
+    }
+
+    #[inline]
+    /// Returns true if the map is empty.
+    pub fn is_empty(&self) -> bool {
+        self.keys.is_empty()
+    }
+
+    #[inline]
+    /// Returns the set of ids with an entry in the map.
+    pub fn keys(&self) -> &IdSet<T> {
+        &self.keys
+    }
+
+    /// Removes all entries from the map.
+    pub fn clear(&mut self) {
+        let keys = mem::take(&mut self.keys);
+        for id in &keys {
+            // Safety: the id was in the set, so the value is initialized. It has been removed
+            // from the set, so it will not be dropped again.
+            unsafe { self.values[id].assume_init_drop() };
+        }
+        self.values.clear();
+    }
+
+    #[inline]
+    /// Returns true if the map has an entry for the id.
+    pub fn contains_key(&self, id: Id) -> bool {
+        self.keys.contains(id)
+    }
+
+    #[inline]
+    /// Returns a reference to the value for the id.
+    pub fn get(&self, id: Id) -> Option<&V> {
+        if self.keys.contains(id) {
+            // Safety: the id is in the set, so the value is initialized.
+            Some(unsafe { self.values[id].assume_init_ref() })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    /// Returns a mutable reference to the value for the id.
+    pub fn get_mut(&mut self, id: Id) -> Option<&mut V> {
+        if self.keys.contains(id) {
+            // Safety: the id is in the set, so the value is initialized.
+            Some(unsafe { self.values[id].assume_init_mut() })
+        } else {
+            None
+        }
+    }
+
+    /// Inserts a value for the id, returning the previous value if there was one.
+    pub fn insert(&mut self, id: Id, value: V) -> Option<V> {
+        match self.get_mut(id) {
+            Some(old) => Some(mem::replace(old, value)),
+            None => {
+                self.insert_vacant(id, value);
+                None
+            }
+        }
+    }
+
+    /// Removes the entry for the id, returning its value if there was one.
+    pub fn remove(&mut self, id: Id) -> Option<V> {
+        if self.keys.remove(id) {
+            // Safety: the id was in the set, so the value is initialized. It has been removed
+            // from the set, so it will not be read again.
+            Some(unsafe { self.values[id].assume_init_read() })
+        } else {
+            None
+        }
+    }
+
+    #[inline]
+    /// Returns the entry for the id, for inplace manipulation.
+    pub fn entry(&mut self, id: Id) -> Entry<'_, V, T> {
+        if self.keys.contains(id) {
+            Entry::Occupied(OccupiedEntry { map: self, id })
+        } else {
+            Entry::Vacant(VacantEntry { map: self, id })
+        }
+    }
+
+    /// Removes all entries for which the predicate returns false.
+    pub fn retain<F: FnMut(Id, &mut V) -> bool>(&mut self, mut pred: F) {
+        let IdSet { ref mut blocks, ref mut len } = self.keys;
+        let mut idx = 0;
+        for word in blocks.iter_mut() {
+            let mut block = *word;
+
+            while block != T::ZERO {
+                let id = idx + block.trailing_zeros() as usize;
+                let mask = block.wrapping_sub(T::ONE);
+
+                // Safety: the id is in the set, so the value is initialized.
+                if !pred(id, unsafe { self.values[id].assume_init_mut() }) {
+                    *len -= 1;
+                    *word &= mask;
+                    // Safety: the id has been removed from the set, so the value will not be
+                    // dropped again.
+                    unsafe { self.values[id].assume_init_drop() };
+                }
+                block &= mask;
+            }
+
+            idx += T::BITS;
+        }
+    }
+
+    #[inline]
+    /// Returns an iterator over the entries in increasing order of id.
+    pub fn iter(&self) -> Iter<'_, V, T> {
+        Iter {
+            ids: self.keys.iter(),
+            values: &self.values,
+        }
+    }
+
+    #[inline]
+    /// Returns an iterator over the entries in increasing order of id, with mutable references to
+    /// the values.
+    pub fn iter_mut(&mut self) -> IterMut<'_, V, T> {
+        IterMut {
+            ids: self.keys.iter(),
+            values: &mut self.values,
+            offset: 0,
+        }
+    }
+
+    /// Inserts a value for an id which is not in the map.
+    fn insert_vacant(&mut self, id: Id, value: V) -> &mut V {
+        debug_assert!(!self.keys.contains(id));
+        if id >= self.values.len() {
+            self.values.resize_with(id + 1, MaybeUninit::uninit);
+        }
+        self.keys.insert(id);
+        self.values[id].write(value)
+    }
+}
+
+impl<V, T: BitBlock> Drop for IdMap<V, T> {
+    fn drop(&mut self) {
+        self.clear();
+    }
+}
+
+impl<V: Clone, T: BitBlock> Clone for IdMap<V, T> {
+    fn clone(&self) -> Self {
+        let mut map = IdMap::with_capacity_general(self.values.len());
+        for (id, value) in self {
+            map.insert_vacant(id, value.clone());
+        }
+        map
+    }
+}
+
+impl<V: fmt::Debug, T: BitBlock> fmt::Debug for IdMap<V, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_map().entries(self.iter()).finish()
+    }
+}
+
+impl<V, T: BitBlock> Default for IdMap<V, T> {
+    #[inline]
+    fn default() -> Self {
+        IdMap::new_general()
+    }
+}
+
+impl<V: Eq, T: BitBlock> Eq for IdMap<V, T> {}
+
+impl<V: PartialEq, T: BitBlock> PartialEq for IdMap<V, T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.keys == other.keys && self.iter().zip(other).all(|((_, l), (_, r))| l == r)
+    }
+}
+
+impl<V, T: BitBlock> ops::Index<Id> for IdMap<V, T> {
+    type Output = V;
+
+    #[inline]
+    /// Returns a reference to the value for the id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the id is not in the map.
+    fn index(&self, id: Id) -> &V {
+        self.get(id).expect("no entry found for id")
+    }
+}
+
+impl<V, T: BitBlock> ops::IndexMut<Id> for IdMap<V, T> {
+    #[inline]
+    /// Returns a mutable reference to the value for the id.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the id is not in the map.
+    fn index_mut(&mut self, id: Id) -> &mut V {
+        self.get_mut(id).expect("no entry found for id")
+    }
+}
+
+impl<V, T: BitBlock> Extend<(Id, V)> for IdMap<V, T> {
+    #[inline]
+    fn extend<I: IntoIterator<Item = (Id, V)>>(&mut self, iter: I) {
+        for (id, value) in iter {
+            self.insert(id, value);
+        }
+    }
+}
+
+impl<V, T: BitBlock> FromIterator<(Id, V)> for IdMap<V, T> {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = (Id, V)>>(iter: I) -> Self {
+        let mut map = IdMap::new_general();
+        map.extend(iter);
+        map
+    }
+}
+
+impl<'a, V, T: BitBlock> IntoIterator for &'a IdMap<V, T> {
+    type Item = (Id, &'a V);
+    type IntoIter = Iter<'a, V, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a, V, T: BitBlock> IntoIterator for &'a mut IdMap<V, T> {
+    type Item = (Id, &'a mut V);
+    type IntoIter = IterMut<'a, V, T>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter_mut()
+    }
+}
+
+impl<V, T: BitBlock> IntoIterator for IdMap<V, T> {
+    type Item = (Id, V);
+    type IntoIter = IntoIter<V, T>;
+
+    #[inline]
+    fn into_iter(mut self) -> Self::IntoIter {
+        IntoIter {
+            ids: mem::take(&mut self.keys).into_iter(),
+            values: mem::take(&mut self.values),
+        }
+    }
+}
+
+/// A view into a single entry of an `IdMap`, which may be occupied or vacant.
+pub enum Entry<'a, V: 'a, T: BitBlock + 'a = Block> {
+    /// An entry with a value.
+    Occupied(OccupiedEntry<'a, V, T>),
+    /// An entry without a value.
+    Vacant(VacantEntry<'a, V, T>),
+}
+
+impl<'a, V, T: BitBlock> Entry<'a, V, T> {
+    #[inline]
+    /// Returns the id of the entry.
+    pub fn key(&self) -> Id {
+        match *self {
+            Entry::Occupied(ref entry) => entry.key(),
+            Entry::Vacant(ref entry) => entry.key(),
+        }
+    }
+
+    #[inline]
+    /// Inserts `default` if the entry is vacant, and returns a mutable reference to the value.
+    pub fn or_insert(self, default: V) -> &'a mut V {
+        self.or_insert_with(|| default)
+    }
+
+    #[inline]
+    /// Inserts the result of `default` if the entry is vacant, and returns a mutable reference to
+    /// the value.
+    pub fn or_insert_with<F: FnOnce() -> V>(self, default: F) -> &'a mut V {
+        match self {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(default()),
+        }
+    }
+
+    #[inline]
+    /// Calls `f` with the value if the entry is occupied.
+    pub fn and_modify<F: FnOnce(&mut V)>(mut self, f: F) -> Self {
+        if let Entry::Occupied(ref mut entry) = self {
+            f(entry.get_mut());
+        }
+        self
+    }
+}
+
+impl<'a, V: Default, T: BitBlock> Entry<'a, V, T> {
+    #[inline]
+    /// Inserts the default value if the entry is vacant, and returns a mutable reference to the
+    /// value.
+    pub fn or_default(self) -> &'a mut V {
+        self.or_insert_with(V::default)
+    }
+}
+
+impl<'a, V: fmt::Debug, T: BitBlock> fmt::Debug for Entry<'a, V, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            Entry::Occupied(ref entry) => f.debug_tuple("Entry").field(entry).finish(),
+            Entry::Vacant(ref entry) => f.debug_tuple("Entry").field(entry).finish(),
+        }
+    }
+}
+
+/// An occupied entry of an `IdMap`.
+pub struct OccupiedEntry<'a, V: 'a, T: BitBlock + 'a = Block> {
+    map: &'a mut IdMap<V, T>,
+    id: Id,
+}
+
+impl<'a, V, T: BitBlock> OccupiedEntry<'a, V, T> {
+    #[inline]
+    /// Returns the id of the entry.
+    pub fn key(&self) -> Id {
+        self.id
+    }
+
+    #[inline]
+    /// Returns a reference to the value.
+    pub fn get(&self) -> &V {
+        &self.map[self.id]
+    }
+
+    #[inline]
+    /// Returns a mutable reference to the value.
+    pub fn get_mut(&mut self) -> &mut V {
+        &mut self.map[self.id]
+    }
+
+    #[inline]
+    /// Converts the entry into a mutable reference to the value.
+    pub fn into_mut(self) -> &'a mut V {
+        &mut self.map[self.id]
+    }
+
+    #[inline]
+    /// Replaces the value, returning the old value.
+    pub fn insert(&mut self, value: V) -> V {
+        mem::replace(self.get_mut(), value)
+    }
+
+    #[inline]
+    /// Removes the entry from the map, returning its value.
+    pub fn remove(self) -> V {
+        self.map.remove(self.id).unwrap()
+    }
+}
+
+impl<'a, V: fmt::Debug, T: BitBlock> fmt::Debug for OccupiedEntry<'a, V, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("OccupiedEntry")
+            .field("key", &self.id)
+            .field("value", self.get())
+            .finish()
+    }
+}
+
+/// A vacant entry of an `IdMap`.
+pub struct VacantEntry<'a, V: 'a, T: BitBlock + 'a = Block> {
+    map: &'a mut IdMap<V, T>,
+    id: Id,
+}
+
+impl<'a, V, T: BitBlock> VacantEntry<'a, V, T> {
+    #[inline]
+    /// Returns the id of the entry.
+    pub fn key(&self) -> Id {
+        self.id
+    }
+
+    #[inline]
+    /// Inserts a value for the entry, returning a mutable reference to it.
+    pub fn insert(self, value: V) -> &'a mut V {
+        self.map.insert_vacant(self.id, value)
+    }
+}
+
+impl<'a, V, T: BitBlock> fmt::Debug for VacantEntry<'a, V, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_tuple("VacantEntry").field(&self.id).finish()
+    }
+}
+
+/// An iterator over the entries of an `IdMap` in increasing order of id.
+pub struct Iter<'a, V: 'a, T: BitBlock + 'a = Block> {
+    ids: super::Iter<'a, T>,
+    values: &'a [MaybeUninit<V>],
+}
+
+impl<'a, V, T: BitBlock> Iter<'a, V, T> {
+    #[inline]
+    fn entry(&self, id: Id) -> (Id, &'a V) {
+        // Safety: the id is in the set, so the value is initialized.
+        (id, unsafe { self.values[id].assume_init_ref() })
+    }
+}
+
+impl<'a, V, T: BitBlock> Iterator for Iter<'a, V, T> {
+    type Item = (Id, &'a V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.ids.next().map(|id| self.entry(id))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
+
+impl<'a, V, T: BitBlock> DoubleEndedIterator for Iter<'a, V, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.ids.next_back().map(|id| self.entry(id))
+    }
+}
+
+impl<'a, V, T: BitBlock> ExactSizeIterator for Iter<'a, V, T> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.ids.len()
+    }
+}
+
+impl<'a, V, T: BitBlock> Clone for Iter<'a, V, T> {
+    #[inline]
+    fn clone(&self) -> Self {
+        Iter {
+            ids: self.ids.clone(),
+            values: self.values,
+        }
+    }
+}
+
+impl<'a, V: fmt::Debug, T: BitBlock> fmt::Debug for Iter<'a, V, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_list().entries(self.clone()).finish()
+    }
+}
+
+/// An iterator over the entries of an `IdMap` in increasing order of id, with mutable references
+/// to the values.
+pub struct IterMut<'a, V: 'a, T: BitBlock + 'a = Block> {
+    ids: super::Iter<'a, T>,
+    // The values which have not been yielded yet, starting from the value of id `offset`.
+    values: &'a mut [MaybeUninit<V>],
+    offset: usize,
+}
+
+impl<'a, V, T: BitBlock> Iterator for IterMut<'a, V, T> {
+    type Item = (Id, &'a mut V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        let id = self.ids.next()?;
+        let values = mem::take(&mut self.values);
+        let (value, rest) = values[id - self.offset..].split_first_mut().unwrap();
+        self.values = rest;
+        self.offset = id + 1;
+        // Safety: the id is in the set, so the value is initialized.
+        Some((id, unsafe { value.assume_init_mut() }))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
+
+impl<'a, V, T: BitBlock> DoubleEndedIterator for IterMut<'a, V, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        let id = self.ids.next_back()?;
+        let values = mem::take(&mut self.values);
+        let (value, rest) = values[..id + 1 - self.offset].split_last_mut().unwrap();
+        self.values = rest;
+        // Safety: the id is in the set, so the value is initialized.
+        Some((id, unsafe { value.assume_init_mut() }))
+    }
+}
+
+impl<'a, V, T: BitBlock> ExactSizeIterator for IterMut<'a, V, T> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.ids.len()
+    }
+}
+
+impl<'a, V, T: BitBlock> fmt::Debug for IterMut<'a, V, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IterMut").field("ids", &self.ids).finish()
+    }
+}
+
+/// A consuming iterator over the entries of an `IdMap` in increasing order of id.
+pub struct IntoIter<V, T: BitBlock = Block> {
+    ids: super::IntoIter<T>,
+    // The value for each id remaining in `ids` is initialized.
+    values: Vec<MaybeUninit<V>>,
+}
+
+impl<V, T: BitBlock> IntoIter<V, T> {
+    #[inline]
+    fn entry(&mut self, id: Id) -> (Id, V) {
+        // Safety: the id was in the set, so the value is initialized. It has been removed from the
+        // iterator, so it will not be read again.
+        (id, unsafe { self.values[id].assume_init_read() })
+    }
+}
+
+impl<V, T: BitBlock> Iterator for IntoIter<V, T> {
+    type Item = (Id, V);
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.ids.next().map(|id| self.entry(id))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.ids.size_hint()
+    }
+}
+
+impl<V, T: BitBlock> DoubleEndedIterator for IntoIter<V, T> {
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.ids.next_back().map(|id| self.entry(id))
+    }
+}
+
+impl<V, T: BitBlock> ExactSizeIterator for IntoIter<V, T> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.ids.len()
+    }
+}
+
+impl<V, T: BitBlock> Drop for IntoIter<V, T> {
+    fn drop(&mut self) {
+        self.for_each(drop);
+    }
+}
+
+impl<V, T: BitBlock> fmt::Debug for IntoIter<V, T> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("IntoIter").field("ids", &self.ids).finish()
+    }
+}
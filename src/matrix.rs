@@ -0,0 +1,210 @@
+use super::{ceil_div, BitBlock, Block, BlockIter, Blocks, Id, IdSet, UnionAll};
+use block::{mask, pop_lsb};
+
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+/// A matrix of bits, stored as a vector of `IdSet` rows. Entry `(i, j)` is set if row `i` contains
+/// `j`.
+///
+/// A square matrix can be used as the adjacency matrix of a directed graph, where row `i` is the
+/// set of vertices with an edge from vertex `i`.
+///
+/// # Examples
+///
+/// ```
+/// # use id_set::BitMatrix;
+/// #
+/// let mut graph = BitMatrix::new(4, 4);
+/// graph.insert(0, 1);
+/// graph.insert(1, 2);
+/// graph.insert(3, 0);
+///
+/// let reachable = graph.transitive_closure();
+/// assert_eq!(reachable.row(3).iter().collect::<Vec<_>>(), [0, 1, 2]);
+/// assert_eq!(reachable.column(2).iter().collect::<Vec<_>>(), [0, 1, 3]);
+/// assert_eq!(graph.transpose().row(0).iter().collect::<Vec<_>>(), [3]);
+/// ```
+pub struct BitMatrix<T: BitBlock = Block> {
+    rows: Vec<IdSet<T>>,
+    // Every row only contains elements less than this.
+    cols: usize,
+}
+
+impl BitMatrix {
+    #[inline]
+    /// Creates a matrix with the given number of rows and columns, with no bits set.
+    pub fn new(rows: usize, cols: usize) -> Self {
+        BitMatrix::new_general(rows, cols)
+    }
+}
+
+impl<T: BitBlock> BitMatrix<T> {
+    /// Creates a matrix with any block type with the given number of rows and columns, with no
+    /// bits set.
+    pub fn new_general(rows: usize, cols: usize) -> Self {
+        BitMatrix {
+            rows: (0..rows).map(|_| IdSet::new_general()).collect(),
+            cols,
+        }
+    }
+
+    /// Creates a matrix from its rows.
+    ///
+    /// # Panics
+    ///
+    /// Panics if any row contains an element not less than `cols`.
+    pub fn from_rows(rows: Vec<IdSet<T>>, cols: usize) -> Self {
+        assert!(rows.iter().filter_map(IdSet::last).all(|j| j < cols),
+                "row contains an element outside the matrix");
+        BitMatrix { rows, cols }
+    }
+
+    #[inline]
+    /// Returns the number of rows.
+    pub fn num_rows(&self) -> usize {
+        self.rows.len()
+    }
+
+    #[inline]
+    /// Returns the number of columns.
+    pub fn num_cols(&self) -> usize {
+        self.cols
+    }
+
+    #[inline]
+    /// Returns the set of columns with a bit set in row `i`.
+    pub fn row(&self, i: usize) -> &IdSet<T> {
+        &self.rows[i]
+    }
+
+    #[inline]
+    /// Returns the rows of the matrix.
+    pub fn rows(&self) -> &[IdSet<T>] {
+        &self.rows
+    }
+
+    #[inline]
+    /// Consumes the matrix and returns its rows.
+    pub fn into_rows(self) -> Vec<IdSet<T>> {
+        self.rows
+    }
+
+    #[inline]
+    /// Sets the bit at `(i, j)`, returning true if it was not already set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `(i, j)` is outside the matrix.
+    pub fn insert(&mut self, i: usize, j: usize) -> bool {
+        assert!(j < self.cols, "column {} outside the matrix", j);
+        self.rows[i].insert(j)
+    }
+
+    #[inline]
+    /// Clears the bit at `(i, j)`, returning true if it was set.
+    pub fn remove(&mut self, i: usize, j: usize) -> bool {
+        self.rows[i].remove(j)
+    }
+
+    #[inline]
+    /// Returns true if the bit at `(i, j)` is set.
+    pub fn contains(&self, i: usize, j: usize) -> bool {
+        self.rows[i].contains(j)
+    }
+
+    /// Returns the set of rows with a bit set in column `j`.
+    ///
+    /// Each block of the result is gathered from the corresponding block of `T::BITS` rows.
+    pub fn column(&self, j: usize) -> IdSet<T> {
+        assert!(j < self.cols, "column {} outside the matrix", j);
+        let (word, bit) = (j / T::BITS, j % T::BITS);
+        let blocks = self.rows
+            .chunks(T::BITS)
+            .map(|rows| {
+                rows.iter().enumerate().fold(T::ZERO, |acc, (k, row)| {
+                    let block = row.as_blocks().get(word).cloned().unwrap_or(T::ZERO);
+                    acc | ((block >> bit) & T::ONE) << k
+                })
+            })
+            .collect();
+        IdSet::from_blocks(blocks)
+    }
+
+    /// Takes the union of the given rows.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use id_set::{BitMatrix, IdSet};
+    /// #
+    /// let mut graph = BitMatrix::new(3, 3);
+    /// graph.insert(0, 1);
+    /// graph.insert(2, 0);
+    /// graph.insert(2, 2);
+    ///
+    /// // The vertices adjacent to either 0 or 2.
+    /// let vertices: IdSet = vec![0, 2].into_iter().collect();
+    /// assert_eq!(graph.row_union(&vertices).collect::<Vec<_>>(), [0, 1, 2]);
+    /// ```
+    pub fn row_union<I>(&self, rows: I) -> BlockIter<UnionAll<Blocks<'_, T>>>
+        where I: IntoIterator<Item = Id>
+    {
+        IdSet::union_all(rows.into_iter().map(|i| &self.rows[i]))
+    }
+
+    /// Returns the transpose of the matrix.
+    pub fn transpose(&self) -> Self {
+        let words = ceil_div(self.rows.len(), T::BITS);
+        let mut blocks = vec![vec![T::ZERO; words]; self.cols];
+        for (i, row) in self.rows.iter().enumerate() {
+            let (word, bit) = (i / T::BITS, i % T::BITS);
+            for (idx, &block) in row.as_blocks().iter().enumerate() {
+                let mut block = block;
+                while block != T::ZERO {
+                    let j = idx * T::BITS + pop_lsb(&mut block);
+                    blocks[j][word] |= mask(bit);
+                }
+            }
+        }
+        BitMatrix {
+            rows: blocks.into_iter().map(IdSet::from_blocks).collect(),
+            cols: self.rows.len(),
+        }
+    }
+
+    /// Returns the transitive closure of a square matrix, using Warshall's algorithm. Bit
+    /// `(i, j)` of the result is set if there is a path of at least one edge from `i` to `j` in
+    /// the graph with this adjacency matrix.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the matrix is not square.
+    pub fn transitive_closure(&self) -> Self {
+        assert_eq!(self.rows.len(), self.cols, "matrix is not square");
+        let mut closure = self.clone();
+        for k in 0..self.cols {
+            let (before, rest) = closure.rows.split_at_mut(k);
+            let (pivot, after) = rest.split_first_mut().unwrap();
+            for row in before.iter_mut().chain(after) {
+                if row.contains(k) {
+                    *row |= &*pivot;
+                }
+            }
+        }
+        closure
+    }
+
+    /// Returns the product of two matrices over the boolean semiring. Bit `(i, j)` of the result
+    /// is set if there is some `k` such that bit `(i, k)` of `self` and bit `(k, j)` of `other`
+    /// are set.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the number of columns of `self` is not the number of rows of `other`.
+    pub fn product(&self, other: &BitMatrix<T>) -> Self {
+        assert_eq!(self.cols, other.rows.len(), "matrix dimensions do not match");
+        BitMatrix {
+            rows: self.rows.iter().map(|row| other.row_union(row).into_set()).collect(),
+            cols: other.cols,
+        }
+    }
+}
@@ -0,0 +1,240 @@
+use super::{BitBlock, BlockIter, IdSet, IntoBlockIterator};
+
+impl IdSet {
+    /// Takes the union of any number of block iterators.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use id_set::IdSet;
+    /// #
+    /// let sets: Vec<IdSet> = (0..4).map(|n| (n * 10..n * 10 + 3).collect()).collect();
+    /// assert_eq!(IdSet::union_all(&sets).collect::<Vec<_>>(),
+    ///            [0, 1, 2, 10, 11, 12, 20, 21, 22, 30, 31, 32]);
+    /// ```
+    pub fn union_all<I>(iters: I) -> BlockIter<UnionAll<<I::Item as IntoBlockIterator>::Blocks>>
+        where I: IntoIterator,
+              I::Item: IntoBlockIterator
+    {
+        let sources = sources(iters);
+        let len = sources.iter().map(|source| source.len()).max().unwrap_or(0);
+        BlockIter::new(UnionAll { sources, len })
+    }
+
+    /// Takes the intersection of any number of block iterators. The intersection of no iterators
+    /// is empty.
+    pub fn intersection_all<I>(iters: I)
+                               -> BlockIter<IntersectionAll<<I::Item as IntoBlockIterator>::Blocks>>
+        where I: IntoIterator,
+              I::Item: IntoBlockIterator
+    {
+        let sources = sources(iters);
+        let len = sources.iter().map(|source| source.len()).min().unwrap_or(0);
+        BlockIter::new(IntersectionAll { sources, len })
+    }
+
+    /// Returns the elements present in at least `k` of any number of block iterators.
+    ///
+    /// Each block is combined with a bit-sliced counter, so this takes time proportional to the
+    /// number of blocks multiplied by the number of iterators, regardless of the number of
+    /// elements.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `k` is zero.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # use id_set::IdSet;
+    /// #
+    /// let a: IdSet = (0..10).collect();
+    /// let b: IdSet = (5..15).collect();
+    /// let c: IdSet = (8..20).collect();
+    /// assert_eq!(IdSet::threshold(vec![&a, &b, &c], 2).collect::<Vec<_>>(),
+    ///            (5..15).collect::<Vec<_>>());
+    /// ```
+    pub fn threshold<I>(iters: I, k: usize)
+                        -> BlockIter<Threshold<<I::Item as IntoBlockIterator>::Blocks>>
+        where I: IntoIterator,
+              I::Item: IntoBlockIterator
+    {
+        assert!(k != 0, "threshold must be at least one");
+        let sources = sources(iters);
+        // Only blocks present in at least k sources can contain elements.
+        let mut lens: Vec<_> = sources.iter().map(|source| source.len()).collect();
+        lens.sort_unstable_by(|a, b| b.cmp(a));
+        let len = lens.get(k - 1).cloned().unwrap_or(0);
+        // The number of bits needed to count up to the number of sources.
+        let planes = (usize::BITS - sources.len().leading_zeros()) as usize;
+        BlockIter::new(Threshold {
+                           sources,
+                           len,
+                           k,
+                           counter: vec![<I::Item as IntoBlockIterator>::Block::ZERO; planes],
+                       })
+    }
+}
+
+/// Collects the raw block iterators of the sources.
+fn sources<I>(iters: I) -> Vec<<I::Item as IntoBlockIterator>::Blocks>
+    where I: IntoIterator,
+          I::Item: IntoBlockIterator
+{
+    iters.into_iter().map(|iter| iter.into_block_iter().into_inner()).collect()
+}
+
+#[derive(Clone, Debug)]
+/// Takes the union of any number of block iterators.
+pub struct UnionAll<B> {
+    sources: Vec<B>,
+    len: usize,
+}
+
+impl<B> Iterator for UnionAll<B>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
+{
+    type Item = B::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(self.sources
+                 .iter_mut()
+                 .filter_map(Iterator::next)
+                 .fold(B::Item::ZERO, |acc, block| acc | block))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<B> ExactSizeIterator for UnionAll<B>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
+{
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Takes the intersection of any number of block iterators. Iteration ends as soon as any of the
+/// iterators is exhausted.
+pub struct IntersectionAll<B> {
+    sources: Vec<B>,
+    len: usize,
+}
+
+impl<B> Iterator for IntersectionAll<B>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
+{
+    type Item = B::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+        Some(self.sources
+                 .iter_mut()
+                 .filter_map(Iterator::next)
+                 .fold(B::Item::MAX, |acc, block| acc & block))
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<B> ExactSizeIterator for IntersectionAll<B>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
+{
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Takes the elements present in at least `k` of any number of block iterators.
+pub struct Threshold<B: Iterator> {
+    sources: Vec<B>,
+    len: usize,
+    k: usize,
+    // A bit-sliced counter: bit i of the count for each element is stored in block i.
+    counter: Vec<B::Item>,
+}
+
+impl<B> Iterator for Threshold<B>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
+{
+    type Item = B::Item;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.len == 0 {
+            return None;
+        }
+        self.len -= 1;
+
+        for plane in &mut self.counter {
+            *plane = B::Item::ZERO;
+        }
+        for block in self.sources.iter_mut().filter_map(Iterator::next) {
+            // Add the block to the counter, propagating carries to higher planes.
+            let mut carry = block;
+            for plane in &mut self.counter {
+                if carry == B::Item::ZERO {
+                    break;
+                }
+                let sum = *plane ^ carry;
+                carry &= *plane;
+                *plane = sum;
+            }
+        }
+
+        // Compare the counter with k, starting from the most significant plane. Bits of k above
+        // the counter's width make every count smaller than k.
+        if self.k >> self.counter.len() != 0 {
+            return Some(B::Item::ZERO);
+        }
+        let (mut greater, mut equal) = (B::Item::ZERO, B::Item::MAX);
+        for (bit, &plane) in self.counter.iter().enumerate().rev() {
+            if self.k & (1 << bit) != 0 {
+                equal &= plane;
+            } else {
+                greater |= equal & plane;
+                equal &= !plane;
+            }
+        }
+        Some(greater | equal)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<B> ExactSizeIterator for Threshold<B>
+    where B: ExactSizeIterator,
+          B::Item: BitBlock
+{
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+}
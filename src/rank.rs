@@ -0,0 +1,104 @@
+use std::cmp;
+
+use super::{BitBlock, Block, Id, IdSet, DEFAULT_INLINE};
+use block::{low_mask, select_bit};
+
+/// The number of bits covered by each entry of the index.
+const SUPERBLOCK_BITS: usize = 512;
+
+#[derive(Clone, Debug)]
+/// An auxiliary index over an `IdSet` answering rank queries in constant time and select queries
+/// in logarithmic time.
+///
+/// The index stores the number of elements before each 512-bit superblock. It borrows the set, so
+/// the set cannot be modified while the index is alive; build a new index after modifying it.
+///
+/// # Examples
+///
+/// ```
+/// # use id_set::{IdSet, RankIndex};
+/// #
+/// let set: IdSet = (0..10000).filter(|&n| n % 3 == 0).collect();
+/// let index = RankIndex::new(&set);
+///
+/// assert_eq!(index.rank(3000), 1000);
+/// assert_eq!(index.select(1000), Some(3000));
+/// ```
+pub struct RankIndex<'a, T: BitBlock + 'a = Block, const N: usize = DEFAULT_INLINE> {
+    set: &'a IdSet<T, N>,
+    // The number of elements in all superblocks before each superblock.
+    prefix: Vec<usize>,
+}
+
+impl<'a, T: BitBlock, const N: usize> RankIndex<'a, T, N> {
+    /// Builds an index over the given set.
+    pub fn new(set: &'a IdSet<T, N>) -> Self {
+        let mut count = 0;
+        let prefix = set.as_blocks()
+            .chunks(Self::superblock_len())
+            .map(|chunk| {
+                let before = count;
+                count += chunk.iter().map(|block| block.count_ones() as usize).sum::<usize>();
+                before
+            })
+            .collect();
+        RankIndex { set, prefix }
+    }
+
+    #[inline]
+    fn superblock_len() -> usize {
+        cmp::max(1, SUPERBLOCK_BITS / T::BITS)
+    }
+
+    #[inline]
+    /// Returns the indexed set.
+    pub fn set(&self) -> &'a IdSet<T, N> {
+        self.set
+    }
+
+    /// Returns the number of elements in the set less than the given element.
+    pub fn rank(&self, id: Id) -> usize {
+        let blocks = self.set.as_blocks();
+        let (word, bit) = (id / T::BITS, id % T::BITS);
+        if word >= blocks.len() {
+            return self.set.len();
+        }
+        let superblock = word / Self::superblock_len();
+        let start = superblock * Self::superblock_len();
+        let within: usize = blocks[start..word]
+            .iter()
+            .map(|block| block.count_ones() as usize)
+            .sum();
+        self.prefix[superblock] + within + (blocks[word] & low_mask(bit)).count_ones() as usize
+    }
+
+    /// Returns the k-th smallest element of the set, counting from zero, or `None` if the set has
+    /// k or fewer elements.
+    pub fn select(&self, k: usize) -> Option<Id> {
+        if k >= self.set.len() {
+            return None;
+        }
+        // The last superblock whose prefix count is at most k contains the element.
+        let superblock = match self.prefix.binary_search(&k) {
+            Ok(mut idx) => {
+                // Skip past empty superblocks with the same prefix count.
+                while idx + 1 < self.prefix.len() && self.prefix[idx + 1] == k {
+                    idx += 1;
+                }
+                idx
+            }
+            Err(idx) => idx - 1,
+        };
+
+        let mut k = k - self.prefix[superblock];
+        let start = superblock * Self::superblock_len();
+        for (word, &block) in self.set.as_blocks()[start..].iter().enumerate() {
+            let ones = block.count_ones() as usize;
+            if k < ones {
+                return Some((start + word) * T::BITS + select_bit(block, k));
+            }
+            k -= ones;
+        }
+        unreachable!()
+    }
+}
@@ -0,0 +1,155 @@
+//! Parallel iteration and set operations, enabled with the `rayon` feature.
+//!
+//! Work is split at block boundaries, in chunks large enough that each thread handles a
+//! substantial run of blocks. The in-place operations count the set bits of each chunk as they go
+//! and sum the counts to update the element count, so no further pass over the blocks is needed.
+//!
+//! ```
+//! # extern crate id_set;
+//! # extern crate rayon;
+//! # use id_set::IdSet;
+//! # use rayon::prelude::*;
+//! #
+//! # fn main() {
+//! let a: IdSet = (0..100_000usize).into_par_iter().filter(|n| n % 3 == 0).collect();
+//! let b: IdSet = (0..100_000usize).into_par_iter().filter(|n| n % 5 == 0).collect();
+//! let both = a.par_intersection(&b);
+//! assert_eq!(both.len(), 6667);
+//! assert_eq!(both.par_iter().sum::<usize>(), (0..100_000).step_by(15).sum());
+//! # }
+//! ```
+
+use std::cmp;
+
+use rayon_crate::iter::plumbing::UnindexedConsumer;
+use rayon_crate::prelude::*;
+
+use super::{count_ones, simd, BitBlock, Block, Id, IdIter, IdSet};
+use simd::Op;
+
+/// The number of bits handled by each parallel task.
+const CHUNK_BITS: usize = 1 << 16;
+
+/// Returns the number of blocks handled by each parallel task.
+#[inline]
+fn chunk_len<T: BitBlock>() -> usize {
+    cmp::max(CHUNK_BITS / T::BITS, 1)
+}
+
+impl<T: BitBlock, const N: usize> IdSet<T, N> {
+    #[inline]
+    /// Returns a parallel iterator over the elements of the set. Elements are produced in no
+    /// particular order.
+    pub fn par_iter(&self) -> ParIter<'_, T> {
+        ParIter { blocks: self.as_blocks() }
+    }
+
+    /// Returns the union of the set with another, computed in parallel.
+    pub fn par_union<const M: usize>(&self, other: &IdSet<T, M>) -> Self {
+        let mut set = self.clone();
+        set.par_inplace_union(other);
+        set
+    }
+
+    /// Returns the intersection of the set with another, computed in parallel.
+    pub fn par_intersection<const M: usize>(&self, other: &IdSet<T, M>) -> Self {
+        let mut set = self.clone();
+        set.par_inplace_intersection(other);
+        set
+    }
+
+    /// Takes the inplace union of the set with another, in parallel.
+    pub fn par_inplace_union<const M: usize>(&mut self, other: &IdSet<T, M>) {
+        let rhs = other.as_blocks();
+        let n = cmp::min(self.blocks.len(), rhs.len());
+        if rhs.len() > n {
+            self.blocks.extend(rhs[n..].iter().cloned());
+        }
+        self.len = par_apply(&mut self.blocks[..n], &rhs[..n], Op::Or) +
+                   par_count_ones(&self.blocks[n..]);
+    }
+
+    /// Takes the inplace intersection of the set with another, in parallel.
+    pub fn par_inplace_intersection<const M: usize>(&mut self, other: &IdSet<T, M>) {
+        let rhs = other.as_blocks();
+        if rhs.len() < self.blocks.len() {
+            self.blocks.drain(rhs.len()).for_each(drop);
+        }
+        let n = cmp::min(self.blocks.len(), rhs.len());
+        self.len = par_apply(&mut self.blocks[..n], &rhs[..n], Op::And);
+    }
+}
+
+/// Applies `op` to corresponding blocks of two slices of the same length in parallel, returning the
+/// number of set bits in the result.
+fn par_apply<T: BitBlock>(lhs: &mut [T], rhs: &[T], op: Op) -> usize {
+    let chunk = chunk_len::<T>();
+    lhs.par_chunks_mut(chunk)
+        .zip(rhs.par_chunks(chunk))
+        .map(|(l, r)| simd::apply(l, r, op))
+        .sum()
+}
+
+/// Returns the total number of set bits in the blocks, counted in parallel.
+fn par_count_ones<T: BitBlock>(blocks: &[T]) -> usize {
+    blocks.par_chunks(chunk_len::<T>()).map(count_ones).sum()
+}
+
+#[derive(Clone, Debug)]
+/// A parallel iterator over the elements of a set.
+pub struct ParIter<'a, T: 'a = Block> {
+    blocks: &'a [T],
+}
+
+impl<'a, T: BitBlock> ParallelIterator for ParIter<'a, T> {
+    type Item = Id;
+
+    fn drive_unindexed<C>(self, consumer: C) -> C::Result
+        where C: UnindexedConsumer<Self::Item>
+    {
+        let chunk = chunk_len::<T>();
+        self.blocks
+            .par_chunks(chunk)
+            .enumerate()
+            .flat_map_iter(move |(idx, blocks)| {
+                let offset = idx * chunk * T::BITS;
+                IdIter::new(blocks.iter().cloned()).map(move |id| id + offset)
+            })
+            .drive_unindexed(consumer)
+    }
+}
+
+impl<'a, T: BitBlock, const N: usize> IntoParallelIterator for &'a IdSet<T, N> {
+    type Iter = ParIter<'a, T>;
+    type Item = Id;
+
+    #[inline]
+    fn into_par_iter(self) -> Self::Iter {
+        self.par_iter()
+    }
+}
+
+impl<T: BitBlock, const N: usize> FromParallelIterator<Id> for IdSet<T, N> {
+    fn from_par_iter<I>(iter: I) -> Self
+        where I: IntoParallelIterator<Item = Id>
+    {
+        iter.into_par_iter()
+            .fold(IdSet::new_general, |mut set, id| {
+                set.insert(id);
+                set
+            })
+            .reduce(IdSet::new_general, |mut lhs, rhs| {
+                lhs |= &rhs;
+                lhs
+            })
+    }
+}
+
+impl<T: BitBlock, const N: usize> ParallelExtend<Id> for IdSet<T, N> {
+    fn par_extend<I>(&mut self, iter: I)
+        where I: IntoParallelIterator<Item = Id>
+    {
+        let set: Self = iter.into_par_iter().collect();
+        self.par_inplace_union(&set);
+    }
+}
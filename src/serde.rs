@@ -0,0 +1,156 @@
+//! Serialization support, enabled with the `serde` feature.
+//!
+//! By default an `IdSet` is serialized in its compact form: a struct holding the element count and
+//! the blocks of the underlying representation, with trailing zero blocks dropped. The [`as_ids`]
+//! module can be used with `#[serde(with = "...")]` to serialize a set as a sequence of elements
+//! instead.
+//!
+//! When deserializing, the element count is recomputed from the blocks and an error is returned if
+//! it does not match the serialized count.
+//!
+//! ```
+//! # extern crate id_set;
+//! # extern crate serde;
+//! # extern crate serde_json;
+//! # use id_set::IdSet;
+//! # use serde::{Deserialize, Serialize};
+//! #
+//! #[derive(Serialize, Deserialize)]
+//! struct Entity {
+//!     tags: IdSet,
+//!     #[serde(with = "id_set::serde::as_ids")]
+//!     friends: IdSet,
+//! }
+//!
+//! # fn main() {
+//! let entity = Entity {
+//!     tags: (0..3).collect(),
+//!     friends: vec![10, 20].into_iter().collect(),
+//! };
+//! assert_eq!(serde_json::to_string(&entity).unwrap(),
+//!            r#"{"tags":{"len":3,"blocks":[7]},"friends":[10,20]}"#);
+//! # }
+//! ```
+//!
+//! [`as_ids`]: as_ids/index.html
+
+use std::fmt;
+
+use serde_crate::{de, Deserialize, Deserializer, Serialize, Serializer};
+
+use super::{BitBlock, IdSet};
+use store::BlockStore;
+
+#[derive(Serialize)]
+#[serde(crate = "serde_crate", rename = "IdSet")]
+struct CompactRef<'a, T: 'a> {
+    len: usize,
+    blocks: &'a [T],
+}
+
+#[derive(Deserialize)]
+#[serde(crate = "serde_crate", rename = "IdSet")]
+struct Compact<T> {
+    len: usize,
+    blocks: Vec<T>,
+}
+
+impl<T, const N: usize> Serialize for IdSet<T, N>
+    where T: BitBlock + Serialize
+{
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let blocks = self.as_blocks();
+        let used = blocks.iter().rposition(|&block| block != T::ZERO).map_or(0, |idx| idx + 1);
+        CompactRef {
+                len: self.len,
+                blocks: &blocks[..used],
+            }
+            .serialize(serializer)
+    }
+}
+
+impl<'de, T, const N: usize> Deserialize<'de> for IdSet<T, N>
+    where T: BitBlock + Deserialize<'de>
+{
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let compact = Compact::<T>::deserialize(deserializer)?;
+        let len = compact.blocks.iter().map(|block| block.count_ones() as usize).sum();
+        if len != compact.len {
+            return Err(de::Error::custom(format_args!("invalid element count {}, the blocks \
+                                                       contain {} elements",
+                                                      compact.len,
+                                                      len)));
+        }
+        Ok(IdSet {
+               blocks: BlockStore::Heap(compact.blocks),
+               len,
+           })
+    }
+}
+
+/// Serializes a set as a sequence of its elements in increasing order, for use with
+/// `#[serde(with = "id_set::serde::as_ids")]`.
+pub mod as_ids {
+    use super::*;
+
+    /// Serializes the set as a sequence of elements.
+    pub fn serialize<T, const N: usize, S>(set: &IdSet<T, N>,
+                                           serializer: S)
+                                           -> Result<S::Ok, S::Error>
+        where T: BitBlock,
+              S: Serializer
+    {
+        serializer.collect_seq(set)
+    }
+
+    /// Deserializes a set from a sequence of elements. Duplicate elements are ignored.
+    pub fn deserialize<'de, T, const N: usize, D>(deserializer: D)
+                                                  -> Result<IdSet<T, N>, D::Error>
+        where T: BitBlock,
+              D: Deserializer<'de>
+    {
+        deserializer.deserialize_seq(IdsVisitor(IdSet::new_general()))
+    }
+
+    struct IdsVisitor<T: BitBlock, const N: usize>(IdSet<T, N>);
+
+    impl<'de, T: BitBlock, const N: usize> de::Visitor<'de> for IdsVisitor<T, N> {
+        type Value = IdSet<T, N>;
+
+        fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+            f.write_str("a sequence of ids")
+        }
+
+        fn visit_seq<A: de::SeqAccess<'de>>(mut self, mut seq: A) -> Result<Self::Value, A::Error> {
+            while let Some(id) = seq.next_element()? {
+                self.0.insert(id);
+            }
+            Ok(self.0)
+        }
+    }
+}
+
+/// Serializes a set in its compact form, for use with
+/// `#[serde(with = "id_set::serde::as_blocks")]`. This is the same as the default representation.
+pub mod as_blocks {
+    use super::*;
+
+    /// Serializes the set as its element count and blocks.
+    pub fn serialize<T, const N: usize, S>(set: &IdSet<T, N>,
+                                           serializer: S)
+                                           -> Result<S::Ok, S::Error>
+        where T: BitBlock + Serialize,
+              S: Serializer
+    {
+        set.serialize(serializer)
+    }
+
+    /// Deserializes a set from its element count and blocks.
+    pub fn deserialize<'de, T, const N: usize, D>(deserializer: D)
+                                                  -> Result<IdSet<T, N>, D::Error>
+        where T: BitBlock + Deserialize<'de>,
+              D: Deserializer<'de>
+    {
+        IdSet::deserialize(deserializer)
+    }
+}
@@ -0,0 +1,244 @@
+//! Bulk operations over slices of blocks.
+//!
+//! The operations are bitwise, so the blocks are processed as bytes whatever the block type. On x86
+//! with AVX2, detected at runtime, 256 bits are combined at a time in a vector register. Their set
+//! bits are counted with a lookup table of the counts for each nibble, and the byte counts are only
+//! widened and summed once per batch of vectors. The compiler manages much the same for `u64`
+//! blocks on its own, but not for the narrower ones. Otherwise the bytes are combined and counted
+//! as `u64` words. Any remaining bytes are handled one at a time.
+use std::{mem, slice};
+
+use super::BitBlock;
+
+#[derive(Copy, Clone, Debug)]
+/// A bitwise operation on pairs of blocks.
+pub enum Op {
+    And,
+    Or,
+    Xor,
+    AndNot,
+}
+
+impl Op {
+    #[inline(always)]
+    fn eval<T: BitBlock>(self, l: T, r: T) -> T {
+        match self {
+            Op::And => l & r,
+            Op::Or => l | r,
+            Op::Xor => l ^ r,
+            Op::AndNot => l & !r,
+        }
+    }
+}
+
+/// Replaces each block of `lhs` with `op` applied to it and the corresponding block of `rhs`, and
+/// returns the number of set bits in the result. Both slices must have the same length.
+#[inline]
+pub fn apply<T: BitBlock>(lhs: &mut [T], rhs: &[T], op: Op) -> usize {
+    debug_assert_eq!(lhs.len(), rhs.len());
+    let (lhs, rhs) = (bytes_mut(lhs), bytes(rhs));
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // Safety: the processor supports the enabled features.
+            return unsafe { avx2::apply(lhs, rhs, op) };
+        }
+    }
+    apply_words(lhs, rhs, op)
+}
+
+/// Returns the number of set bits in `op` applied to each pair of corresponding blocks of `lhs` and
+/// `rhs`. Both slices must have the same length.
+#[inline]
+pub fn count<T: BitBlock>(lhs: &[T], rhs: &[T], op: Op) -> usize {
+    debug_assert_eq!(lhs.len(), rhs.len());
+    let (lhs, rhs) = (bytes(lhs), bytes(rhs));
+    #[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+    {
+        if is_x86_feature_detected!("avx2") {
+            // Safety: the processor supports the enabled features.
+            return unsafe { avx2::count(lhs, rhs, op) };
+        }
+    }
+    count_words(lhs, rhs, op)
+}
+
+/// Views a slice of blocks as bytes.
+#[inline]
+fn bytes<T: BitBlock>(blocks: &[T]) -> &[u8] {
+    // Safety: `BitBlock` is only implemented for primitive integers, which have no padding.
+    unsafe { slice::from_raw_parts(blocks.as_ptr() as *const u8, mem::size_of_val(blocks)) }
+}
+
+/// Views a mutable slice of blocks as bytes.
+#[inline]
+fn bytes_mut<T: BitBlock>(blocks: &mut [T]) -> &mut [u8] {
+    // Safety: as above, and every bit pattern is a valid primitive integer.
+    unsafe { slice::from_raw_parts_mut(blocks.as_mut_ptr() as *mut u8, mem::size_of_val(blocks)) }
+}
+
+/// The portable version of `apply`, working on `u64` words.
+pub fn apply_words(lhs: &mut [u8], rhs: &[u8], op: Op) -> usize {
+    let (mut lwords, mut rwords) = (lhs.chunks_exact_mut(8), rhs.chunks_exact(8));
+    let mut ones = 0;
+    for (l, r) in (&mut lwords).zip(&mut rwords) {
+        let word = op.eval(read_word(l), read_word(r));
+        l.copy_from_slice(&word.to_ne_bytes());
+        ones += word.count_ones() as usize;
+    }
+    ones + apply_bytes(lwords.into_remainder(), rwords.remainder(), op)
+}
+
+/// The portable version of `count`, working on `u64` words.
+pub fn count_words(lhs: &[u8], rhs: &[u8], op: Op) -> usize {
+    let (lwords, rwords) = (lhs.chunks_exact(8), rhs.chunks_exact(8));
+    let tail = count_bytes(lwords.remainder(), rwords.remainder(), op);
+    lwords.zip(rwords)
+        .map(|(l, r)| op.eval(read_word(l), read_word(r)).count_ones() as usize)
+        .sum::<usize>() + tail
+}
+
+#[inline]
+fn read_word(bytes: &[u8]) -> u64 {
+    let mut word = [0; 8];
+    word.copy_from_slice(bytes);
+    u64::from_ne_bytes(word)
+}
+
+#[inline]
+fn apply_bytes(lhs: &mut [u8], rhs: &[u8], op: Op) -> usize {
+    let mut ones = 0;
+    for (l, &r) in lhs.iter_mut().zip(rhs) {
+        *l = op.eval(*l, r);
+        ones += l.count_ones() as usize;
+    }
+    ones
+}
+
+#[inline]
+fn count_bytes(lhs: &[u8], rhs: &[u8], op: Op) -> usize {
+    lhs.iter().zip(rhs).map(|(&l, &r)| op.eval(l, r).count_ones() as usize).sum()
+}
+
+#[cfg(any(target_arch = "x86", target_arch = "x86_64"))]
+mod avx2 {
+    #[cfg(target_arch = "x86")]
+    use std::arch::x86::*;
+    #[cfg(target_arch = "x86_64")]
+    use std::arch::x86_64::*;
+
+    use std::cmp;
+
+    use super::{apply_bytes, count_bytes, Op};
+
+    /// The number of bytes in a vector.
+    const LANES: usize = 32;
+    /// The number of vectors whose byte counts, at most 8 each, can be summed without overflow.
+    const BATCH: usize = 31;
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn apply(lhs: &mut [u8], rhs: &[u8], op: Op) -> usize {
+        // Each operation gets its own copy of the loop, so that it has no branches.
+        let ones = match op {
+            Op::And => apply_with(lhs, rhs, |l, r| _mm256_and_si256(l, r)),
+            Op::Or => apply_with(lhs, rhs, |l, r| _mm256_or_si256(l, r)),
+            Op::Xor => apply_with(lhs, rhs, |l, r| _mm256_xor_si256(l, r)),
+            Op::AndNot => apply_with(lhs, rhs, |l, r| _mm256_andnot_si256(r, l)),
+        };
+        let tail = lhs.len() / LANES * LANES;
+        ones + apply_bytes(&mut lhs[tail..], &rhs[tail..], op)
+    }
+
+    #[target_feature(enable = "avx2")]
+    pub unsafe fn count(lhs: &[u8], rhs: &[u8], op: Op) -> usize {
+        let ones = match op {
+            Op::And => count_with(lhs, rhs, |l, r| _mm256_and_si256(l, r)),
+            Op::Or => count_with(lhs, rhs, |l, r| _mm256_or_si256(l, r)),
+            Op::Xor => count_with(lhs, rhs, |l, r| _mm256_xor_si256(l, r)),
+            Op::AndNot => count_with(lhs, rhs, |l, r| _mm256_andnot_si256(r, l)),
+        };
+        let tail = lhs.len() / LANES * LANES;
+        ones + count_bytes(&lhs[tail..], &rhs[tail..], op)
+    }
+
+    /// Applies `f` to the whole vectors of the slices, and returns the number of set bits in the
+    /// results.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn apply_with<F>(lhs: &mut [u8], rhs: &[u8], f: F) -> usize
+        where F: Fn(__m256i, __m256i) -> __m256i
+    {
+        let (l, r) = (lhs.as_mut_ptr() as *mut __m256i, rhs.as_ptr() as *const __m256i);
+        sum_counts(lhs.len() / LANES, |i| {
+            let v = f(_mm256_loadu_si256(l.add(i)), _mm256_loadu_si256(r.add(i)));
+            _mm256_storeu_si256(l.add(i), v);
+            v
+        })
+    }
+
+    /// Returns the number of set bits in `f` applied to the whole vectors of the slices.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn count_with<F>(lhs: &[u8], rhs: &[u8], f: F) -> usize
+        where F: Fn(__m256i, __m256i) -> __m256i
+    {
+        let (l, r) = (lhs.as_ptr() as *const __m256i, rhs.as_ptr() as *const __m256i);
+        sum_counts(lhs.len() / LANES, |i| {
+            f(_mm256_loadu_si256(l.add(i)), _mm256_loadu_si256(r.add(i)))
+        })
+    }
+
+    /// Returns the total number of set bits in the vectors `vector(0)` to `vector(len - 1)`.
+    ///
+    /// The vectors are taken four at a time into separate byte counts, which are only widened and
+    /// summed once per batch.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn sum_counts<F>(len: usize, mut vector: F) -> usize
+        where F: FnMut(usize) -> __m256i
+    {
+        let zero = _mm256_setzero_si256();
+        let mut ones = zero;
+        let mut i = 0;
+        while i < len {
+            let end = cmp::min(len, i + BATCH * 4);
+            let mut counts = [zero; 4];
+            while i + 4 <= end {
+                for (j, count) in counts.iter_mut().enumerate() {
+                    *count = _mm256_add_epi8(*count, popcount(vector(i + j)));
+                }
+                i += 4;
+            }
+            while i < end {
+                counts[0] = _mm256_add_epi8(counts[0], popcount(vector(i)));
+                i += 1;
+            }
+            for &count in &counts {
+                ones = _mm256_add_epi64(ones, _mm256_sad_epu8(count, zero));
+            }
+        }
+        sum(ones)
+    }
+
+    /// Returns the number of set bits in each byte.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn popcount(v: __m256i) -> __m256i {
+        // The number of set bits in each nibble, for both 128-bit halves.
+        let table = _mm256_setr_epi8(0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4,
+                                     0, 1, 1, 2, 1, 2, 2, 3, 1, 2, 2, 3, 2, 3, 3, 4);
+        let nibbles = _mm256_set1_epi8(0x0f);
+        let lo = _mm256_shuffle_epi8(table, _mm256_and_si256(v, nibbles));
+        let hi = _mm256_shuffle_epi8(table, _mm256_and_si256(_mm256_srli_epi16(v, 4), nibbles));
+        _mm256_add_epi8(lo, hi)
+    }
+
+    /// Returns the sum of the 64-bit lanes.
+    #[inline]
+    #[target_feature(enable = "avx2")]
+    unsafe fn sum(v: __m256i) -> usize {
+        let mut lanes = [0u64; 4];
+        _mm256_storeu_si256(lanes.as_mut_ptr() as *mut __m256i, v);
+        lanes.iter().sum::<u64>() as usize
+    }
+}
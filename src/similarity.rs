@@ -0,0 +1,97 @@
+use std::cmp;
+
+use super::{BitBlock, IntoBlockIterator};
+
+/// The number of elements in both, only the left and only the right of two sets.
+struct Overlap {
+    both: usize,
+    left: usize,
+    right: usize,
+}
+
+impl Overlap {
+    /// Counts the elements of two sets in one pass over their blocks.
+    #[inline]
+    fn new<L, R>(left: L, right: R) -> Self
+        where L: IntoBlockIterator,
+              R: IntoBlockIterator<Block = L::Block>
+    {
+        let mut left = left.into_block_iter().into_inner();
+        let mut right = right.into_block_iter().into_inner();
+        let mut overlap = Overlap {
+            both: 0,
+            left: 0,
+            right: 0,
+        };
+        loop {
+            match (left.next(), right.next()) {
+                (Some(l), Some(r)) => {
+                    overlap.both += (l & r).count_ones() as usize;
+                    overlap.left += (l & !r).count_ones() as usize;
+                    overlap.right += (r & !l).count_ones() as usize;
+                }
+                (Some(l), None) => overlap.left += l.count_ones() as usize,
+                (None, Some(r)) => overlap.right += r.count_ones() as usize,
+                (None, None) => return overlap,
+            }
+        }
+    }
+}
+
+/// Returns the size of the intersection of two sets divided by the size of their union, or 1 if
+/// both sets are empty.
+///
+/// # Examples
+///
+/// ```
+/// # use id_set::{jaccard, IdSet};
+/// #
+/// let a: IdSet = (0..4).collect();
+/// let b: IdSet = (2..6).collect();
+/// assert_eq!(jaccard(&a, &b), 2.0 / 6.0);
+/// ```
+pub fn jaccard<L, R>(left: L, right: R) -> f64
+    where L: IntoBlockIterator,
+          R: IntoBlockIterator<Block = L::Block>
+{
+    let Overlap { both, left, right } = Overlap::new(left, right);
+    ratio(both, both + left + right)
+}
+
+/// Returns twice the size of the intersection of two sets divided by the sum of their sizes, or 1
+/// if both sets are empty.
+pub fn dice<L, R>(left: L, right: R) -> f64
+    where L: IntoBlockIterator,
+          R: IntoBlockIterator<Block = L::Block>
+{
+    let Overlap { both, left, right } = Overlap::new(left, right);
+    ratio(2 * both, 2 * both + left + right)
+}
+
+/// Returns the size of the intersection of two sets divided by the size of the smaller set, or 1 if
+/// either set is empty.
+pub fn overlap_coefficient<L, R>(left: L, right: R) -> f64
+    where L: IntoBlockIterator,
+          R: IntoBlockIterator<Block = L::Block>
+{
+    let Overlap { both, left, right } = Overlap::new(left, right);
+    ratio(both, both + cmp::min(left, right))
+}
+
+/// Returns the number of elements in exactly one of two sets.
+pub fn hamming_distance<L, R>(left: L, right: R) -> usize
+    where L: IntoBlockIterator,
+          R: IntoBlockIterator<Block = L::Block>
+{
+    let Overlap { left, right, .. } = Overlap::new(left, right);
+    left + right
+}
+
+#[inline]
+fn ratio(num: usize, denom: usize) -> f64 {
+    if denom == 0 {
+        1.0
+    } else {
+        num as f64 / denom as f64
+    }
+}
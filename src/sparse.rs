@@ -0,0 +1,801 @@
+use std::{cmp, fmt, ops, slice};
+use std::cmp::Ordering;
+use std::iter::FromIterator;
+
+use super::{BlockIter, Id, IntoBlockIterator};
+
+/// The number of elements covered by each container.
+const CHUNK_LEN: usize = 1 << 16;
+/// The number of 64-bit words in a bitmap container.
+const BITMAP_WORDS: usize = CHUNK_LEN / 64;
+/// The largest number of elements stored in an array container.
+const ARRAY_MAX: usize = 4096;
+
+type Bitmap = Box<[u64; BITMAP_WORDS]>;
+
+#[derive(Clone, Debug)]
+enum Container {
+    // A sorted array of elements.
+    Array(Vec<u16>),
+    // A bitmap of all elements, and the number of elements.
+    Bitmap(Bitmap, usize),
+    // A sorted list of disjoint, non-adjacent inclusive ranges of elements.
+    Run(Vec<(u16, u16)>),
+}
+
+impl Container {
+    fn len(&self) -> usize {
+        match *self {
+            Container::Array(ref vec) => vec.len(),
+            Container::Bitmap(_, len) => len,
+            Container::Run(ref runs) => {
+                runs.iter().map(|&(start, end)| (end - start) as usize + 1).sum()
+            }
+        }
+    }
+
+    fn max(&self) -> u16 {
+        match *self {
+            Container::Array(ref vec) => vec[vec.len() - 1],
+            Container::Bitmap(ref bits, _) => {
+                let word = bits.iter().rposition(|&word| word != 0).unwrap();
+                (word * 64 + 63 - bits[word].leading_zeros() as usize) as u16
+            }
+            Container::Run(ref runs) => runs[runs.len() - 1].1,
+        }
+    }
+
+    fn contains(&self, low: u16) -> bool {
+        match *self {
+            Container::Array(ref vec) => vec.binary_search(&low).is_ok(),
+            Container::Bitmap(ref bits, _) => bits[low as usize / 64] & (1 << (low % 64)) != 0,
+            Container::Run(ref runs) => {
+                match runs.binary_search_by(|&(start, _)| start.cmp(&low)) {
+                    Ok(_) => true,
+                    Err(0) => false,
+                    Err(idx) => low <= runs[idx - 1].1,
+                }
+            }
+        }
+    }
+
+    fn insert(&mut self, low: u16) -> bool {
+        self.unrun();
+        let inserted = match *self {
+            Container::Array(ref mut vec) => {
+                match vec.binary_search(&low) {
+                    Ok(_) => false,
+                    Err(idx) => {
+                        vec.insert(idx, low);
+                        true
+                    }
+                }
+            }
+            Container::Bitmap(ref mut bits, ref mut len) => {
+                let (word, mask) = (low as usize / 64, 1 << (low % 64));
+                if bits[word] & mask == 0 {
+                    bits[word] |= mask;
+                    *len += 1;
+                    true
+                } else {
+                    false
+                }
+            }
+            Container::Run(_) => unreachable!(),
+        };
+        self.normalize();
+        inserted
+    }
+
+    fn remove(&mut self, low: u16) -> bool {
+        self.unrun();
+        let removed = match *self {
+            Container::Array(ref mut vec) => {
+                match vec.binary_search(&low) {
+                    Ok(idx) => {
+                        vec.remove(idx);
+                        true
+                    }
+                    Err(_) => false,
+                }
+            }
+            Container::Bitmap(ref mut bits, ref mut len) => {
+                let (word, mask) = (low as usize / 64, 1 << (low % 64));
+                if bits[word] & mask != 0 {
+                    bits[word] &= !mask;
+                    *len -= 1;
+                    true
+                } else {
+                    false
+                }
+            }
+            Container::Run(_) => unreachable!(),
+        };
+        self.normalize();
+        removed
+    }
+
+    /// Writes the elements of the container into a zeroed bitmap.
+    fn fill_bitmap(&self, bits: &mut [u64; BITMAP_WORDS]) {
+        match *self {
+            Container::Array(ref vec) => {
+                for &low in vec {
+                    bits[low as usize / 64] |= 1 << (low % 64);
+                }
+            }
+            Container::Bitmap(ref other, _) => bits.copy_from_slice(&other[..]),
+            Container::Run(ref runs) => {
+                for &(start, end) in runs {
+                    for low in start as usize..end as usize + 1 {
+                        bits[low / 64] |= 1 << (low % 64);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Returns the 64-bit word of the container's bitmap with the given index.
+    fn word(&self, word: usize) -> u64 {
+        let (lo, hi) = (word * 64, word * 64 + 63);
+        match *self {
+            Container::Array(ref vec) => {
+                let start = vec.partition_point(|&low| (low as usize) < lo);
+                vec[start..]
+                    .iter()
+                    .take_while(|&&low| low as usize <= hi)
+                    .fold(0, |bits, &low| bits | 1 << (low % 64))
+            }
+            Container::Bitmap(ref bits, _) => bits[word],
+            Container::Run(ref runs) => {
+                let start = runs.partition_point(|&(_, end)| (end as usize) < lo);
+                runs[start..]
+                    .iter()
+                    .take_while(|&&(start, _)| start as usize <= hi)
+                    .fold(0, |bits, &(start, end)| {
+                        let start = cmp::max(start as usize, lo) - lo;
+                        let end = cmp::min(end as usize, hi) - lo;
+                        bits | ((!0 << start) & (!0 >> (63 - end)))
+                    })
+            }
+        }
+    }
+
+    fn to_bitmap(&self) -> Bitmap {
+        let mut bits = Box::new([0; BITMAP_WORDS]);
+        self.fill_bitmap(&mut bits);
+        bits
+    }
+
+    /// Converts a run container into an array or bitmap container so it can be modified.
+    fn unrun(&mut self) {
+        if let Container::Run(_) = *self {
+            let len = self.len();
+            *self = if len <= ARRAY_MAX {
+                Container::Array(self.iter().map(|low| low as u16).collect())
+            } else {
+                Container::Bitmap(self.to_bitmap(), len)
+            };
+        }
+    }
+
+    /// Switches between array and bitmap containers when one passes the size threshold.
+    fn normalize(&mut self) {
+        let replace = match *self {
+            Container::Array(ref vec) if vec.len() > ARRAY_MAX => {
+                let len = vec.len();
+                Container::Bitmap(self.to_bitmap(), len)
+            }
+            Container::Bitmap(_, len) if len <= ARRAY_MAX => {
+                Container::Array(self.iter().map(|low| low as u16).collect())
+            }
+            _ => return,
+        };
+        *self = replace;
+    }
+
+    /// Converts the container into a run container if that is smaller.
+    fn run_optimize(&mut self) {
+        let mut runs: Vec<(u16, u16)> = Vec::new();
+        for low in self.iter() {
+            let low = low as u16;
+            match runs.last_mut() {
+                Some(&mut (_, ref mut end)) if *end as usize + 1 == low as usize => *end = low,
+                _ => runs.push((low, low)),
+            }
+        }
+        let size = match *self {
+            Container::Array(ref vec) => vec.len() * 2,
+            Container::Bitmap(..) => BITMAP_WORDS * 8,
+            Container::Run(_) => return,
+        };
+        if runs.len() * 4 < size {
+            runs.shrink_to_fit();
+            *self = Container::Run(runs);
+        }
+    }
+
+    fn iter(&self) -> ContainerIter<'_> {
+        match *self {
+            Container::Array(ref vec) => ContainerIter::Array(vec.iter()),
+            Container::Bitmap(ref bits, _) => {
+                ContainerIter::Bitmap {
+                    bits,
+                    idx: 0,
+                    word: bits[0],
+                }
+            }
+            Container::Run(ref runs) => {
+                ContainerIter::Run {
+                    runs: runs.iter(),
+                    next: 1,
+                    end: 0,
+                }
+            }
+        }
+    }
+
+    /// Combines two containers with a bitwise operation, avoiding bitmaps when the result is
+    /// bounded by an array.
+    fn combine<F>(&self, other: &Container, op: Op, f: F) -> Container
+        where F: Fn(u64, u64) -> u64
+    {
+        if let Container::Array(ref lhs) = *self {
+            if let Container::Array(ref rhs) = *other {
+                let mut container = Container::Array(merge(lhs, rhs, op));
+                container.normalize();
+                return container;
+            }
+            if op == Op::And || op == Op::Sub {
+                let keep = op == Op::And;
+                return Container::Array(lhs.iter()
+                                            .cloned()
+                                            .filter(|&low| other.contains(low) == keep)
+                                            .collect());
+            }
+        }
+
+        let (mut bits, rhs) = (self.to_bitmap(), other.to_bitmap());
+        let mut len = 0;
+        for (l, &r) in bits.iter_mut().zip(rhs.iter()) {
+            *l = f(*l, r);
+            len += l.count_ones() as usize;
+        }
+        let mut container = Container::Bitmap(bits, len);
+        container.normalize();
+        container
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+enum Op {
+    Or,
+    And,
+    Sub,
+    Xor,
+}
+
+/// Merges two sorted arrays.
+fn merge(lhs: &[u16], rhs: &[u16], op: Op) -> Vec<u16> {
+    let mut out = Vec::with_capacity(match op {
+                                         Op::Or | Op::Xor => lhs.len() + rhs.len(),
+                                         Op::And => cmp::min(lhs.len(), rhs.len()),
+                                         Op::Sub => lhs.len(),
+                                     });
+    let (mut i, mut j) = (0, 0);
+    while i < lhs.len() && j < rhs.len() {
+        match lhs[i].cmp(&rhs[j]) {
+            Ordering::Less => {
+                if op != Op::And {
+                    out.push(lhs[i]);
+                }
+                i += 1;
+            }
+            Ordering::Greater => {
+                if op == Op::Or || op == Op::Xor {
+                    out.push(rhs[j]);
+                }
+                j += 1;
+            }
+            Ordering::Equal => {
+                if op == Op::Or || op == Op::And {
+                    out.push(lhs[i]);
+                }
+                i += 1;
+                j += 1;
+            }
+        }
+    }
+    if op != Op::And {
+        out.extend_from_slice(&lhs[i..]);
+    }
+    if op == Op::Or || op == Op::Xor {
+        out.extend_from_slice(&rhs[j..]);
+    }
+    out
+}
+
+#[derive(Clone, Debug)]
+enum ContainerIter<'a> {
+    Array(slice::Iter<'a, u16>),
+    Bitmap {
+        bits: &'a [u64; BITMAP_WORDS],
+        idx: usize,
+        word: u64,
+    },
+    Run {
+        runs: slice::Iter<'a, (u16, u16)>,
+        next: usize,
+        end: usize,
+    },
+}
+
+impl<'a> Iterator for ContainerIter<'a> {
+    type Item = usize;
+
+    #[inline]
+    fn next(&mut self) -> Option<usize> {
+        match *self {
+            ContainerIter::Array(ref mut iter) => iter.next().map(|&low| low as usize),
+            ContainerIter::Bitmap {
+                bits,
+                ref mut idx,
+                ref mut word,
+            } => {
+                while *word == 0 {
+                    *idx += 1;
+                    if *idx == BITMAP_WORDS {
+                        return None;
+                    }
+                    *word = bits[*idx];
+                }
+                let bit = word.trailing_zeros() as usize;
+                *word &= *word - 1;
+                Some(*idx * 64 + bit)
+            }
+            ContainerIter::Run {
+                ref mut runs,
+                ref mut next,
+                ref mut end,
+            } => {
+                if *next > *end {
+                    let &(start, stop) = runs.next()?;
+                    *next = start as usize;
+                    *end = stop as usize;
+                }
+                *next += 1;
+                Some(*next - 1)
+            }
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+struct Chunk {
+    key: usize,
+    container: Container,
+}
+
+#[derive(Clone, Default)]
+/// A compressed set of `usize` elements for sparse data.
+///
+/// The element space is split into chunks of 2<sup>16</sup> elements, and each non-empty chunk is
+/// stored as a sorted array, a bitmap or a list of runs, whichever suits its contents. Storage
+/// required is proportional to the number of elements rather than the maximum element.
+///
+/// The set can be combined with other `SparseIdSet`s using the operators `|`, `&`, `^` and `-`, and
+/// with any other set through its [`blocks`](#method.blocks) of type `u64`.
+///
+/// # Examples
+///
+/// ```
+/// # use id_set::{IdSet, SparseIdSet};
+/// #
+/// let mut a = SparseIdSet::new();
+/// a.insert(1_000_000_000);
+/// a.insert(7);
+///
+/// let b: SparseIdSet = vec![7, 8].into_iter().collect();
+/// assert_eq!((&a & &b).iter().collect::<Vec<_>>(), [7]);
+///
+/// let c: IdSet<u64> = (0..10).collect();
+/// assert_eq!(a.blocks().intersection(&c).collect::<Vec<_>>(), [7]);
+/// ```
+pub struct SparseIdSet {
+    chunks: Vec<Chunk>,
+    len: usize,
+}
+
+impl SparseIdSet {
+    #[inline]
+    /// Creates an empty `SparseIdSet`.
+    pub fn new() -> Self {
+        SparseIdSet {
+            chunks: Vec::new(),
+            len: 0,
+        }
+    }
+
+    /// Creates a `SparseIdSet` from the elements of any block iterator.
+    pub fn from_blocks<I: IntoBlockIterator>(iter: I) -> Self {
+        iter.into_block_iter().into_iter().collect()
+    }
+
+    #[inline]
+    /// Returns the number of distinct elements in the set.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    #[inline]
+    /// Returns true if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    #[inline]
+    /// Removes all elements from the set.
+    pub fn clear(&mut self) {
+        self.chunks.clear();
+        self.len = 0;
+    }
+
+    #[inline]
+    fn find(&self, key: usize) -> Result<usize, usize> {
+        self.chunks.binary_search_by(|chunk| chunk.key.cmp(&key))
+    }
+
+    /// Inserts the given element into the set, returning true if it was not already in the set.
+    pub fn insert(&mut self, id: Id) -> bool {
+        let (key, low) = (id / CHUNK_LEN, (id % CHUNK_LEN) as u16);
+        let inserted = match self.find(key) {
+            Ok(idx) => self.chunks[idx].container.insert(low),
+            Err(idx) => {
+                self.chunks.insert(idx,
+                                   Chunk {
+                                       key,
+                                       container: Container::Array(vec![low]),
+                                   });
+                true
+            }
+        };
+        if inserted {
+            self.len += 1;
+        }
+        inserted
+    }
+
+    /// Removes the given element from the set, returning true if it was in the set.
+    pub fn remove(&mut self, id: Id) -> bool {
+        let (key, low) = (id / CHUNK_LEN, (id % CHUNK_LEN) as u16);
+        let idx = match self.find(key) {
+            Ok(idx) => idx,
+            Err(_) => return false,
+        };
+        let removed = self.chunks[idx].container.remove(low);
+        if removed {
+            self.len -= 1;
+            if self.chunks[idx].container.len() == 0 {
+                self.chunks.remove(idx);
+            }
+        }
+        removed
+    }
+
+    /// Returns true if the given element is in the set.
+    pub fn contains(&self, id: Id) -> bool {
+        let (key, low) = (id / CHUNK_LEN, (id % CHUNK_LEN) as u16);
+        match self.find(key) {
+            Ok(idx) => self.chunks[idx].container.contains(low),
+            Err(_) => false,
+        }
+    }
+
+    /// Converts containers into run-length encoded form where that uses less memory. Run
+    /// containers are converted back as they are modified.
+    pub fn run_optimize(&mut self) {
+        for chunk in &mut self.chunks {
+            chunk.container.run_optimize();
+        }
+    }
+
+    #[inline]
+    /// An iterator over all elements in increasing order.
+    pub fn iter(&self) -> Iter<'_> {
+        Iter {
+            chunks: self.chunks.iter(),
+            current: None,
+            len: self.len,
+        }
+    }
+
+    /// Returns an iterator over the 64-bit blocks of the set, as if it were stored as a bitmap.
+    /// This allows the set to be combined with any other set.
+    ///
+    /// Like the blocks of an `IdSet`, the iterator yields every block up to the one holding the
+    /// largest element, so iterating it takes time proportional to the largest element rather than
+    /// the number of elements. The blocks between containers are zero and cost nothing to produce,
+    /// and are skipped in constant time by `nth`. Prefer the set operations of `SparseIdSet`
+    /// itself, which combine sets container by container, when both sets are sparse.
+    pub fn blocks(&self) -> BlockIter<Blocks<'_>> {
+        let end = match self.chunks.last() {
+            Some(chunk) => (chunk.key * CHUNK_LEN + chunk.container.max() as usize) / 64 + 1,
+            None => 0,
+        };
+        BlockIter::new(Blocks {
+                           chunks: &self.chunks,
+                           chunk: 0,
+                           idx: 0,
+                           end,
+                       })
+    }
+
+    fn combine<F>(&self, other: &SparseIdSet, op: Op, f: F) -> SparseIdSet
+        where F: Fn(u64, u64) -> u64
+    {
+        let (keep_left, keep_right) = match op {
+            Op::Or | Op::Xor => (true, true),
+            Op::And => (false, false),
+            Op::Sub => (true, false),
+        };
+        let mut chunks = Vec::new();
+        let (mut lhs, mut rhs) = (self.chunks.iter().peekable(), other.chunks.iter().peekable());
+        loop {
+            let order = match (lhs.peek(), rhs.peek()) {
+                (Some(l), Some(r)) => l.key.cmp(&r.key),
+                (Some(_), None) => Ordering::Less,
+                (None, Some(_)) => Ordering::Greater,
+                (None, None) => break,
+            };
+            match order {
+                Ordering::Less => {
+                    let chunk = lhs.next().unwrap();
+                    if keep_left {
+                        chunks.push(chunk.clone());
+                    }
+                }
+                Ordering::Greater => {
+                    let chunk = rhs.next().unwrap();
+                    if keep_right {
+                        chunks.push(chunk.clone());
+                    }
+                }
+                Ordering::Equal => {
+                    let (l, r) = (lhs.next().unwrap(), rhs.next().unwrap());
+                    let container = l.container.combine(&r.container, op, &f);
+                    if container.len() != 0 {
+                        chunks.push(Chunk {
+                                        key: l.key,
+                                        container,
+                                    });
+                    }
+                }
+            }
+        }
+        let len = chunks.iter().map(|chunk| chunk.container.len()).sum();
+        SparseIdSet { chunks, len }
+    }
+
+    /// Takes the union of the set with another. Equivalent to `self | other`.
+    pub fn union(&self, other: &SparseIdSet) -> SparseIdSet {
+        self.combine(other, Op::Or, |l, r| l | r)
+    }
+
+    /// Takes the intersection of the set with another. Equivalent to `self & other`.
+    pub fn intersection(&self, other: &SparseIdSet) -> SparseIdSet {
+        self.combine(other, Op::And, |l, r| l & r)
+    }
+
+    /// Takes the difference of the set with another. Equivalent to `self - other`.
+    pub fn difference(&self, other: &SparseIdSet) -> SparseIdSet {
+        self.combine(other, Op::Sub, |l, r| l & !r)
+    }
+
+    /// Takes the symmetric difference of the set with another. Equivalent to `self ^ other`.
+    pub fn symmetric_difference(&self, other: &SparseIdSet) -> SparseIdSet {
+        self.combine(other, Op::Xor, |l, r| l ^ r)
+    }
+
+    /// Take the union of the set inplace with another set. Equivalent to `*self |= other`.
+    pub fn inplace_union(&mut self, other: &SparseIdSet) {
+        *self = self.union(other);
+    }
+
+    /// Take the intersection of the set inplace with another set. Equivalent to `*self &= other`.
+    pub fn inplace_intersection(&mut self, other: &SparseIdSet) {
+        *self = self.intersection(other);
+    }
+
+    /// Take the difference of the set inplace with another set. Equivalent to `*self -= other`.
+    pub fn inplace_difference(&mut self, other: &SparseIdSet) {
+        *self = self.difference(other);
+    }
+
+    /// Take the symmetric difference of the set inplace with another set. Equivalent to
+    /// `*self ^= other`.
+    pub fn inplace_symmetric_difference(&mut self, other: &SparseIdSet) {
+        *self = self.symmetric_difference(other);
+    }
+
+    /// Returns true if the sets are disjoint.
+    pub fn is_disjoint(&self, other: &SparseIdSet) -> bool {
+        self.intersection(other).is_empty()
+    }
+
+    /// Returns true if self is a superset of other.
+    pub fn is_superset(&self, other: &SparseIdSet) -> bool {
+        other.is_subset(self)
+    }
+
+    /// Returns true if self is a subset of other.
+    pub fn is_subset(&self, other: &SparseIdSet) -> bool {
+        self.len <= other.len && self.difference(other).is_empty()
+    }
+}
+
+impl fmt::Debug for SparseIdSet {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl Eq for SparseIdSet {}
+
+impl PartialEq for SparseIdSet {
+    fn eq(&self, other: &Self) -> bool {
+        self.len == other.len && self.iter().eq(other.iter())
+    }
+}
+
+impl Extend<Id> for SparseIdSet {
+    #[inline]
+    fn extend<I: IntoIterator<Item = Id>>(&mut self, iter: I) {
+        for id in iter {
+            self.insert(id);
+        }
+    }
+}
+
+impl FromIterator<Id> for SparseIdSet {
+    #[inline]
+    fn from_iter<I: IntoIterator<Item = Id>>(iter: I) -> Self {
+        let mut set = SparseIdSet::new();
+        set.extend(iter);
+        set
+    }
+}
+
+impl<'a> IntoIterator for &'a SparseIdSet {
+    type Item = Id;
+    type IntoIter = Iter<'a>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<'a> IntoBlockIterator for &'a SparseIdSet {
+    type Block = u64;
+    type Blocks = Blocks<'a>;
+
+    #[inline]
+    fn into_block_iter(self) -> BlockIter<Self::Blocks> {
+        self.blocks()
+    }
+}
+
+macro_rules! sparse_ops {
+    ($($op:ident $method:ident $assign:ident $assign_method:ident $set_method:ident,)*) => {$(
+        impl<'a, 'b> ops::$op<&'b SparseIdSet> for &'a SparseIdSet {
+            type Output = SparseIdSet;
+
+            #[inline]
+            fn $method(self, other: &'b SparseIdSet) -> SparseIdSet {
+                self.$set_method(other)
+            }
+        }
+
+        impl<'a> ops::$assign<&'a SparseIdSet> for SparseIdSet {
+            #[inline]
+            fn $assign_method(&mut self, other: &'a SparseIdSet) {
+                *self = self.$set_method(other);
+            }
+        }
+    )*}
+}
+
+sparse_ops! {
+    BitOr bitor BitOrAssign bitor_assign union,
+    BitAnd bitand BitAndAssign bitand_assign intersection,
+    BitXor bitxor BitXorAssign bitxor_assign symmetric_difference,
+    Sub sub SubAssign sub_assign difference,
+}
+
+#[derive(Clone, Debug)]
+/// An iterator over the elements of a `SparseIdSet` in increasing order.
+pub struct Iter<'a> {
+    chunks: slice::Iter<'a, Chunk>,
+    current: Option<(usize, ContainerIter<'a>)>,
+    len: usize,
+}
+
+impl<'a> Iterator for Iter<'a> {
+    type Item = Id;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some((base, ref mut iter)) = self.current {
+                if let Some(low) = iter.next() {
+                    self.len -= 1;
+                    return Some(base + low);
+                }
+            }
+            let chunk = self.chunks.next()?;
+            self.current = Some((chunk.key * CHUNK_LEN, chunk.container.iter()));
+        }
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        (self.len, Some(self.len))
+    }
+}
+
+impl<'a> ExactSizeIterator for Iter<'a> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.len
+    }
+}
+
+#[derive(Clone, Debug)]
+/// An iterator over the 64-bit blocks of a `SparseIdSet`.
+pub struct Blocks<'a> {
+    chunks: &'a [Chunk],
+    // The index of the first chunk which may contain the next block.
+    chunk: usize,
+    // The index of the next block.
+    idx: usize,
+    end: usize,
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = u64;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.idx == self.end {
+            return None;
+        }
+        let (key, word) = (self.idx / BITMAP_WORDS, self.idx % BITMAP_WORDS);
+        self.idx += 1;
+        while self.chunks[self.chunk].key < key {
+            self.chunk += 1;
+        }
+        let chunk = &self.chunks[self.chunk];
+        if chunk.key != key {
+            return Some(0);
+        }
+        Some(chunk.container.word(word))
+    }
+
+    #[inline]
+    fn nth(&mut self, n: usize) -> Option<Self::Item> {
+        self.idx += cmp::min(n, self.len());
+        self.next()
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl<'a> ExactSizeIterator for Blocks<'a> {
+    #[inline]
+    fn len(&self) -> usize {
+        self.end - self.idx
+    }
+}
@@ -1,25 +1,22 @@
 use std::{iter, ops, slice, vec};
 
-use super::{Block, BITS};
+use super::{BitBlock, Block, DEFAULT_INLINE};
 use BlockStore::{Stack, Heap};
 
-/// The number of blocks that fit into the 196-bit footprint of a vector.
-const SIZE: usize = 196 / BITS;
-
 #[derive(Clone, Debug)]
-pub enum BlockStore {
-    Stack([Block; SIZE]),
-    Heap(Vec<Block>),
+pub enum BlockStore<T, const N: usize> {
+    Stack([T; N]),
+    Heap(Vec<T>),
 }
 
-impl BlockStore {
+impl<T: BitBlock, const N: usize> BlockStore<T, N> {
     pub fn new() -> Self {
-        Stack([0; SIZE])
+        Stack([T::ZERO; N])
     }
 
     pub fn with_capacity(cap: usize) -> Self {
-        if cap <= SIZE {
-            Stack([0; SIZE])
+        if cap <= N {
+            Stack([T::ZERO; N])
         } else {
             Heap(Vec::with_capacity(cap))
         }
@@ -29,7 +26,7 @@ impl BlockStore {
         if let Heap(ref mut vec) = *self {
             vec.clear()
         } else {
-            *self = Stack([0; SIZE])
+            *self = Stack([T::ZERO; N])
         }
     }
 
@@ -37,12 +34,12 @@ impl BlockStore {
         if let Heap(ref vec) = *self {
             vec.capacity()
         } else {
-            SIZE
+            N
         }
     }
 
     pub fn reserve(&mut self, cap: usize) {
-        if SIZE < cap {
+        if N < cap {
             let vec = match *self {
                 Stack(ref arr) => {
                     let mut vec = Vec::with_capacity(cap);
@@ -62,14 +59,12 @@ impl BlockStore {
         let arr = match *self {
             Stack(_) => return,
             Heap(ref mut vec) => {
-                while let Some(&0) = vec.last() {
+                while vec.last() == Some(&T::ZERO) {
                     vec.pop();
                 }
-                if vec.len() <= SIZE {
-                    let mut arr = [0; SIZE];
-                    for i in 0..vec.len() {
-                        arr[i] = vec[i];
-                    }
+                if vec.len() <= N {
+                    let mut arr = [T::ZERO; N];
+                    arr[..vec.len()].copy_from_slice(vec);
                     arr
                 } else {
                     vec.shrink_to_fit();
@@ -80,13 +75,13 @@ impl BlockStore {
         *self = Stack(arr);
     }
 
-    pub fn drain(&mut self, idx: usize) -> Drain {
+    pub fn drain(&mut self, idx: usize) -> Drain<'_, T, N> {
         match *self {
             Stack(ref mut data) => {
-                assert!(idx <= SIZE);
+                assert!(idx <= N);
                 Drain::Stack {
                     data,
-                    idx: idx as u8,
+                    idx,
                 }
             }
             Heap(ref mut vec) => Drain::Heap(vec.drain(idx..)),
@@ -96,59 +91,66 @@ impl BlockStore {
     pub fn resize(&mut self, new_len: usize) {
         let vec = match *self {
             Stack(ref mut arr) => {
-                if new_len < SIZE {
+                if new_len < N {
                     for block in arr {
-                        *block = 0;
+                        *block = T::ZERO;
                     }
                     return;
                 } else {
                     let mut vec = Vec::with_capacity(new_len);
                     vec.extend(&*arr);
-                    vec.resize(new_len, 0);
+                    vec.resize(new_len, T::ZERO);
                     vec
                 }
             }
             Heap(ref mut vec) => {
-                vec.resize(new_len, 0);
+                vec.resize(new_len, T::ZERO);
                 return;
             }
         };
         *self = Heap(vec);
     }
 
-    pub fn iter(&self) -> Iter {
-        Iter { inner: ops::Deref::deref(self).iter() }
+    pub fn into_vec(self) -> Vec<T> {
+        match self {
+            Stack(arr) => arr.to_vec(),
+            Heap(vec) => vec,
+        }
+    }
+
+    pub fn iter(&self) -> Iter<'_, T> {
+        Iter::new(self)
     }
 
-    pub fn iter_mut(&mut self) -> slice::IterMut<Block> {
+    pub fn iter_mut(&mut self) -> slice::IterMut<'_, T> {
         ops::DerefMut::deref_mut(self).iter_mut()
     }
 }
 
-impl Default for BlockStore {
+impl<T: BitBlock, const N: usize> Default for BlockStore<T, N> {
     fn default() -> Self {
         BlockStore::new()
     }
 }
 
-impl Extend<Block> for BlockStore {
+impl<T: BitBlock, const N: usize> Extend<T> for BlockStore<T, N> {
     fn extend<I>(&mut self, iter: I)
-        where I: IntoIterator<Item = Block>
+        where I: IntoIterator<Item = T>
     {
         let iter = iter.into_iter();
         let arr = match *self {
             Stack(arr) => arr,
             Heap(ref mut vec) => return vec.extend(iter),
         };
-        let mut vec = Vec::with_capacity(SIZE.saturating_add(iter.size_hint().0));
+        let mut vec = Vec::with_capacity(N.saturating_add(iter.size_hint().0));
         vec.extend(&arr);
         vec.extend(iter);
         *self = Heap(vec);
     }
 }
 
-impl ops::Deref for BlockStore {
-    type Target = [Block];
+impl<T, const N: usize> ops::Deref for BlockStore<T, N> {
+    type Target = [T];
 
     fn deref(&self) -> &Self::Target {
         match *self {
@@ -158,7 +160,7 @@ impl ops::Deref for BlockStore {
     }
 }
 
-impl ops::DerefMut for BlockStore {
+impl<T, const N: usize> ops::DerefMut for BlockStore<T, N> {
     fn deref_mut(&mut self) -> &mut Self::Target {
         match *self {
             Stack(ref mut arr) => arr,
@@ -167,30 +169,30 @@ impl ops::DerefMut for BlockStore {
     }
 }
 
-impl<'a> iter::FromIterator<&'a Block> for BlockStore {
+impl<'a, T: BitBlock, const N: usize> iter::FromIterator<&'a T> for BlockStore<T, N> {
     fn from_iter<I>(iter: I) -> Self
-        where I: IntoIterator<Item = &'a Block>
+        where I: IntoIterator<Item = &'a T>
     {
         BlockStore::from_iter(iter.into_iter().cloned())
     }
 }
 
-impl iter::FromIterator<Block> for BlockStore {
+impl<T: BitBlock, const N: usize> iter::FromIterator<T> for BlockStore<T, N> {
     fn from_iter<I>(iter: I) -> Self
-        where I: IntoIterator<Item = Block>
+        where I: IntoIterator<Item = T>
     {
         let mut iter = iter.into_iter();
-        if iter.size_hint().0 < SIZE {
-            let mut arr = [0; SIZE];
-            for i in 0..SIZE {
+        if iter.size_hint().0 < N {
+            let mut arr = [T::ZERO; N];
+            for slot in &mut arr {
                 if let Some(block) = iter.next() {
-                    arr[i] = block;
+                    *slot = block;
                 } else {
                     return Stack(arr);
                 }
             }
             if let Some(block) = iter.next() {
-                let mut vec = Vec::with_capacity((SIZE + 1).saturating_add(iter.size_hint().0));
+                let mut vec = Vec::with_capacity((N + 1).saturating_add(iter.size_hint().0));
                 vec.extend(&arr);
                 vec.push(block);
                 vec.extend(iter);
@@ -204,23 +206,23 @@ impl iter::FromIterator<Block> for BlockStore {
     }
 }
 
-impl<'a> IntoIterator for &'a BlockStore {
-    type Item = Block;
-    type IntoIter = Iter<'a>;
+impl<'a, T: BitBlock, const N: usize> IntoIterator for &'a BlockStore<T, N> {
+    type Item = T;
+    type IntoIter = Iter<'a, T>;
 
     fn into_iter(self) -> Self::IntoIter {
         self.iter()
     }
 }
 
-impl IntoIterator for BlockStore {
-    type Item = Block;
-    type IntoIter = IntoIter;
+impl<T: BitBlock, const N: usize> IntoIterator for BlockStore<T, N> {
+    type Item = T;
+    type IntoIter = IntoIter<T, N>;
 
     fn into_iter(self) -> Self::IntoIter {
         IntoIter {
             kind: match self {
-                Stack(data) => IntoIterKind::Stack { data, idx: 0 },
+                Stack(data) => IntoIterKind::Stack { data, idx: 0, end: N },
                 Heap(vec) => IntoIterKind::Heap(vec.into_iter()),
             },
         }
@@ -229,12 +231,18 @@ impl IntoIterator for BlockStore {
 
 #[derive(Clone, Debug)]
 /// An iterator over the blocks of the underlying representation.
-pub struct Iter<'a> {
-    inner: slice::Iter<'a, Block>,
+pub struct Iter<'a, T: 'a = Block> {
+    inner: slice::Iter<'a, T>,
 }
 
-impl<'a> Iterator for Iter<'a> {
-    type Item = Block;
+impl<'a, T> Iter<'a, T> {
+    pub(crate) fn new(blocks: &'a [T]) -> Self {
+        Iter { inner: blocks.iter() }
+    }
+}
+
+impl<'a, T: BitBlock> Iterator for Iter<'a, T> {
+    type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
         self.inner.next().cloned()
@@ -245,7 +253,13 @@ impl<'a> Iterator for Iter<'a> {
     }
 }
 
-impl<'a> ExactSizeIterator for Iter<'a> {
+impl<'a, T: BitBlock> DoubleEndedIterator for Iter<'a, T> {
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().cloned()
+    }
+}
+
+impl<'a, T: BitBlock> ExactSizeIterator for Iter<'a, T> {
     fn len(&self) -> usize {
         self.inner.len()
     }
@@ -253,29 +267,30 @@ impl<'a> ExactSizeIterator for Iter<'a> {
 
 #[derive(Clone, Debug)]
 /// A consuming iterator over the blocks of the underlying representation.
-pub struct IntoIter {
-    kind: IntoIterKind,
+pub struct IntoIter<T = Block, const N: usize = DEFAULT_INLINE> {
+    kind: IntoIterKind<T, N>,
 }
 
 #[derive(Clone, Debug)]
-enum IntoIterKind {
-    Stack { data: [Block; SIZE], idx: u8 },
-    Heap(vec::IntoIter<Block>),
+enum IntoIterKind<T, const N: usize> {
+    Stack { data: [T; N], idx: usize, end: usize },
+    Heap(vec::IntoIter<T>),
 }
 
-impl Iterator for IntoIter {
-    type Item = Block;
+impl<T: BitBlock, const N: usize> Iterator for IntoIter<T, N> {
+    type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
         match self.kind {
             IntoIterKind::Stack {
                 ref data,
                 ref mut idx,
+                end,
             } => {
-                if *idx as usize == SIZE {
+                if *idx == end {
                     None
                 } else {
-                    let ret = data[*idx as usize];
+                    let ret = data[*idx];
                     *idx += 1;
                     Some(ret)
                 }
@@ -285,33 +300,51 @@ impl Iterator for IntoIter {
     }
 
     fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl<T: BitBlock, const N: usize> DoubleEndedIterator for IntoIter<T, N> {
+    fn next_back(&mut self) -> Option<Self::Item> {
         match self.kind {
-            IntoIterKind::Stack { idx, .. } => (SIZE - idx as usize, Some(SIZE - idx as usize)),
-            IntoIterKind::Heap(ref vec) => vec.size_hint(),
+            IntoIterKind::Stack {
+                ref data,
+                idx,
+                ref mut end,
+            } => {
+                if idx == *end {
+                    None
+                } else {
+                    *end -= 1;
+                    Some(data[*end])
+                }
+            }
+            IntoIterKind::Heap(ref mut vec) => vec.next_back(),
         }
     }
 }
 
-impl ExactSizeIterator for IntoIter {
+impl<T: BitBlock, const N: usize> ExactSizeIterator for IntoIter<T, N> {
     fn len(&self) -> usize {
         match self.kind {
-            IntoIterKind::Stack { idx, .. } => SIZE - idx as usize,
+            IntoIterKind::Stack { idx, end, .. } => end - idx,
             IntoIterKind::Heap(ref vec) => vec.len(),
         }
     }
 }
 
 #[derive(Debug)]
-pub enum Drain<'a> {
+pub enum Drain<'a, T: 'a, const N: usize> {
     Stack {
-        data: &'a mut [Block; SIZE],
-        idx: u8,
+        data: &'a mut [T; N],
+        idx: usize,
     },
-    Heap(vec::Drain<'a, Block>),
+    Heap(vec::Drain<'a, T>),
 }
 
-impl<'a> Iterator for Drain<'a> {
-    type Item = Block;
+impl<'a, T: BitBlock, const N: usize> Iterator for Drain<'a, T, N> {
+    type Item = T;
 
     fn next(&mut self) -> Option<Self::Item> {
         match *self {
@@ -319,11 +352,11 @@ impl<'a> Iterator for Drain<'a> {
                 ref mut data,
                 ref mut idx,
             } => {
-                if *idx as usize == SIZE {
+                if *idx == N {
                     None
                 } else {
-                    let ret = data[*idx as usize];
-                    data[*idx as usize] = 0;
+                    let ret = data[*idx];
+                    data[*idx] = T::ZERO;
                     *idx += 1;
                     Some(ret)
                 }
@@ -334,16 +367,16 @@ impl<'a> Iterator for Drain<'a> {
 
     fn size_hint(&self) -> (usize, Option<usize>) {
         match *self {
-            Drain::Stack { idx, .. } => (SIZE - idx as usize, Some(SIZE - idx as usize)),
+            Drain::Stack { idx, .. } => (N - idx, Some(N - idx)),
             Drain::Heap(ref vec) => vec.size_hint(),
         }
     }
 }
 
-impl<'a> ExactSizeIterator for Drain<'a> {
+impl<'a, T: BitBlock, const N: usize> ExactSizeIterator for Drain<'a, T, N> {
     fn len(&self) -> usize {
         match *self {
-            Drain::Stack { idx, .. } => SIZE - idx as usize,
+            Drain::Stack { idx, .. } => N - idx,
             Drain::Heap(ref vec) => vec.len(),
         }
     }
@@ -351,9 +384,8 @@ impl<'a> ExactSizeIterator for Drain<'a> {
 
 #[test]
 fn size() {
-    use std::{mem, u8};
+    use std::mem;
 
-    assert_eq!(mem::size_of::<[Block; SIZE]>(),
+    assert_eq!(mem::size_of::<[Block; DEFAULT_INLINE]>(),
                mem::size_of::<Vec<Block>>());
-    assert!(SIZE <= u8::MAX as usize);
 }
@@ -377,4 +377,966 @@ fn block_iter_into_set() {
     let actual: IdSet = iter.into_set();
 
     assert_eq!(expected, actual);
-}
\ No newline at end of file
+}
+
+fn check_block_type<T: BitBlock>() {
+    let a: IdSet<T> = (0..300).filter(|&n| n % 3 == 0).collect();
+    let b: IdSet<T> = (0..200).filter(|&n| n % 2 == 0).collect();
+
+    assert_eq!(a.len(), 100);
+    assert!(a.contains(297));
+    assert!(!a.contains(298));
+    assert!(a.as_blocks().len() * T::BITS > 297);
+
+    let expected: Vec<_> = (0..200).filter(|&n| n % 6 == 0).collect();
+    assert_eq!(a.intersection(&b).collect::<Vec<_>>(), expected);
+    assert_eq!((&a & &b).into_set().len(), expected.len());
+
+    let mut c = a.clone();
+    c |= &b;
+    assert_eq!(c, (&a | &b).into_set());
+    c -= &b;
+    assert_eq!(c, (&a - &b).into_set());
+
+    let filled = IdSet::<T>::new_filled_general(T::BITS + 1);
+    assert_eq!(filled.iter().collect::<Vec<_>>(), (0..T::BITS + 1).collect::<Vec<_>>());
+}
+
+#[test]
+fn block_types() {
+    check_block_type::<u8>();
+    check_block_type::<u16>();
+    check_block_type::<u32>();
+    check_block_type::<u64>();
+    check_block_type::<u128>();
+}
+
+#[test]
+fn inline_size() {
+    let mut a = IdSet::<Block, 32>::new_general();
+    assert_eq!(a.capacity(), 32 * BITS);
+    a.extend(0..1000);
+    assert_eq!(a.capacity(), 32 * BITS);
+
+    let b: IdSet = (500..1500).collect();
+    let mut c = IdSet::<Block, 0>::new_general();
+    c |= &a & &b;
+    assert_eq!(c, (500..1000).collect::<IdSet>());
+    assert!(c.is_subset(&a));
+
+    a -= &b;
+    assert_eq!(a.len(), 500);
+    assert_eq!(a.clone().into_iter().collect::<Vec<_>>(), (0..500).collect::<Vec<_>>());
+
+    a.clear();
+    a.shrink_to_fit();
+    assert_eq!(a.capacity(), 32 * BITS);
+}
+
+#[test]
+fn ranges() {
+    let mut a = IdSet::new();
+    assert_eq!(a.insert_range(5..70), 65);
+    assert_eq!(a.len(), 65);
+    assert_eq!(a.iter().collect::<Vec<_>>(), (5..70).collect::<Vec<_>>());
+    assert_eq!(a.insert_range(60..=300), 231);
+    assert_eq!(a.insert_range(10..20), 0);
+    assert_eq!(a.len(), 296);
+
+    assert!(a.contains_range(5..301));
+    assert!(!a.contains_range(4..10));
+    assert!(!a.contains_range(300..302));
+    assert!(!a.contains_range(5..));
+    assert!(a.contains_range(1000..1000));
+    assert!(a.intersects_range(..6));
+    assert!(a.intersects_range(300..));
+    assert!(!a.intersects_range(..5));
+    assert!(!a.intersects_range(301..));
+
+    assert_eq!(a.remove_range(32..64), 32);
+    assert_eq!(a.remove_range(200..), 101);
+    assert_eq!(a.remove_range(1000..2000), 0);
+    assert_eq!(a.len(), 163);
+    let expected: IdSet = (5..32).chain(64..200).collect();
+    assert_eq!(a, expected);
+
+    let mut b = IdSet::<u8>::new_general();
+    b.insert_range(3..5);
+    assert_eq!(b.as_blocks()[0], 0b11000);
+    assert_eq!(b.remove_range(..), 2);
+    assert!(b.is_empty());
+}
+
+#[test]
+fn rank_select() {
+    let set: IdSet = (0..5000).filter(|&n| n % 7 == 0 || (1000..3000).contains(&n)).collect();
+    let ids: Vec<_> = set.iter().collect();
+    let index = RankIndex::new(&set);
+
+    for (k, &id) in ids.iter().enumerate() {
+        assert_eq!(set.rank(id), k);
+        assert_eq!(index.rank(id), k);
+        assert_eq!(set.select(k), Some(id));
+        assert_eq!(index.select(k), Some(id));
+    }
+    assert_eq!(set.rank(1_000_000), set.len());
+    assert_eq!(index.rank(1_000_000), set.len());
+    assert_eq!(set.select(set.len()), None);
+    assert_eq!(index.select(set.len()), None);
+
+    // Empty superblocks in the middle of the set.
+    let sparse: IdSet<u64> = vec![3, 5000, 5001, 20000].into_iter().collect();
+    let index = RankIndex::new(&sparse);
+    assert_eq!(index.select(1), Some(5000));
+    assert_eq!(index.select(3), Some(20000));
+    assert_eq!(index.rank(19999), 3);
+}
+
+#[test]
+fn navigation() {
+    let mut set: IdSet = vec![3, 31, 32, 100, 500].into_iter().collect();
+
+    assert_eq!(set.first(), Some(3));
+    assert_eq!(set.last(), Some(500));
+    assert_eq!(set.next_after(3), Some(31));
+    assert_eq!(set.next_after(31), Some(32));
+    assert_eq!(set.next_after(33), Some(100));
+    assert_eq!(set.next_after(500), None);
+    assert_eq!(set.next_after(usize::MAX), None);
+    assert_eq!(set.prev_before(3), None);
+    assert_eq!(set.prev_before(32), Some(31));
+    assert_eq!(set.prev_before(100), Some(32));
+    assert_eq!(set.prev_before(10000), Some(500));
+
+    assert_eq!(set.pop_first(), Some(3));
+    assert_eq!(set.pop_last(), Some(500));
+    assert_eq!(set.pop_last(), Some(100));
+    assert_eq!(set.len(), 2);
+    assert_eq!(set.first(), Some(31));
+    assert_eq!(set.last(), Some(32));
+
+    set.clear();
+    assert_eq!(set.first(), None);
+    assert_eq!(set.last(), None);
+    assert_eq!(set.pop_first(), None);
+}
+
+#[test]
+fn double_ended() {
+    let set: IdSet = vec![0, 3, 31, 32, 100, 191, 192, 500].into_iter().collect();
+    let ids: Vec<_> = set.iter().collect();
+    let rev: Vec<_> = ids.iter().cloned().rev().collect();
+
+    assert_eq!(set.iter().rev().collect::<Vec<_>>(), rev);
+    assert_eq!(set.clone().into_iter().rev().collect::<Vec<_>>(), rev);
+
+    let mut iter = set.iter();
+    assert_eq!(iter.next(), Some(0));
+    assert_eq!(iter.next_back(), Some(500));
+    assert_eq!(iter.next_back(), Some(192));
+    assert_eq!(iter.len(), 5);
+    assert_eq!(iter.next(), Some(3));
+    assert_eq!(iter.collect::<Vec<_>>(), [31, 32, 100, 191]);
+
+    let mut iter = IdSet::from_bytes(&[0b1011]).into_iter();
+    assert_eq!(iter.next_back(), Some(3));
+    assert_eq!(iter.next(), Some(0));
+    assert_eq!(iter.next_back(), Some(1));
+    assert_eq!(iter.next(), None);
+    assert_eq!(iter.next_back(), None);
+}
+
+fn check_rev<B>(iter: BlockIter<B>)
+    where B: Clone + DoubleEndedIterator + ExactSizeIterator,
+          B::Item: BitBlock
+{
+    let mut expected: Vec<Id> = iter.clone().into_iter().collect();
+    expected.reverse();
+    assert_eq!(iter.into_iter().rev().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn combinators_rev() {
+    let a: IdSet<u8> = vec![1, 5, 9, 40, 70].into_iter().collect();
+    let b: IdSet<u8> = vec![5, 8, 9, 200, 300].into_iter().collect();
+
+    check_rev(&a | &b);
+    check_rev(&b | &a);
+    check_rev(&a & &b);
+    check_rev(&b & &a);
+    check_rev(&a ^ &b);
+    check_rev(&b ^ &a);
+    check_rev(&a - &b);
+    check_rev(&b - &a);
+}
+
+#[test]
+fn range() {
+    let set: IdSet = (0..300).filter(|&n| n % 5 == 0).collect();
+
+    let expected: Vec<_> = (0..300).filter(|&n| n % 5 == 0 && (33..=200).contains(&n)).collect();
+    assert_eq!(set.range(33..=200).collect::<Vec<_>>(), expected);
+    assert_eq!(set.range(33..201).rev().collect::<Vec<_>>(),
+               expected.iter().cloned().rev().collect::<Vec<_>>());
+    assert_eq!(set.range(1..5).next(), None);
+    assert_eq!(set.range(5..6).collect::<Vec<_>>(), [5]);
+    assert_eq!(set.range(290..).collect::<Vec<_>>(), [290, 295]);
+    assert_eq!(set.range(..10).collect::<Vec<_>>(), [0, 5]);
+    assert_eq!(set.range(1000..).next(), None);
+    assert_eq!(set.range(10..10).next(), None);
+}
+
+#[test]
+fn allocator() {
+    let mut ids = IdAllocator::<u8>::new_general();
+    for n in 0..20 {
+        assert_eq!(ids.alloc(), n);
+    }
+    assert!(ids.free(3));
+    assert!(ids.free(9));
+    assert!(!ids.free(9));
+    assert!(!ids.free(100));
+    assert_eq!(ids.generation(9), 1);
+    assert_eq!(ids.generation(10), 0);
+
+    assert_eq!(ids.alloc_range(2), 20..22);
+    assert_eq!(ids.alloc(), 3);
+    assert_eq!(ids.alloc(), 9);
+    assert!(ids.is_current(9, 1));
+    assert!(!ids.is_current(9, 0));
+
+    ids.free(5);
+    ids.free(6);
+    ids.free(7);
+    ids.free(12);
+    assert_eq!(ids.alloc_range(3), 5..8);
+    assert_eq!(ids.alloc_range(0), 12..12);
+    assert_eq!(ids.alloc_range(1), 12..13);
+    assert_eq!(ids.len(), 22);
+    assert_eq!(ids.iter_live().collect::<Vec<_>>(), (0..22).collect::<Vec<_>>());
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn serde() {
+    extern crate serde_json;
+
+    let set: IdSet = vec![1, 5, 40].into_iter().collect();
+    let json = serde_json::to_string(&set).unwrap();
+    assert_eq!(json, r#"{"len":3,"blocks":[34,256]}"#);
+    assert_eq!(serde_json::from_str::<IdSet>(&json).unwrap(), set);
+    assert_eq!(serde_json::from_str::<IdSet<u32, 0>>(&json).unwrap(), set);
+    assert!(serde_json::from_str::<IdSet>(r#"{"len":4,"blocks":[34,256]}"#).is_err());
+    assert_eq!(serde_json::to_string(&IdSet::new()).unwrap(), r#"{"len":0,"blocks":[]}"#);
+
+    let mut ser = serde_json::Serializer::new(Vec::new());
+    ::serde::as_ids::serialize(&set, &mut ser).unwrap();
+    assert_eq!(ser.into_inner(), b"[1,5,40]");
+    let mut de = serde_json::Deserializer::from_str("[40,1,5,5]");
+    let ids: IdSet<u8> = ::serde::as_ids::deserialize(&mut de).unwrap();
+    assert_eq!(ids.iter().collect::<Vec<_>>(), [1, 5, 40]);
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn rayon() {
+    use rayon_crate::prelude::*;
+
+    let a: IdSet = (0..300_000).filter(|n| n % 3 == 0).collect();
+    let b: IdSet<u32, 0> = (100_000..500_000).filter(|n| n % 2 == 0).collect();
+
+    let mut ids: Vec<_> = a.par_iter().collect();
+    ids.sort();
+    assert_eq!(ids, a.iter().collect::<Vec<_>>());
+
+    let union = a.par_union(&b);
+    assert_eq!(union, a.union(&b).into_set());
+    assert_eq!(union.len(), a.union(&b).count_ones());
+    let intersection = a.par_intersection(&b);
+    assert_eq!(intersection, a.intersection(&b).into_set());
+    assert_eq!(intersection.len(), a.intersection(&b).count_ones());
+    let mut c = b.clone();
+    c.par_inplace_intersection(&a);
+    assert_eq!(c, intersection);
+    c.par_inplace_union(&IdSet::<u32, 0>::new_general());
+    assert_eq!(c.len(), intersection.len());
+    let mut inline: IdSet = (0..10).collect();
+    inline.par_inplace_union(&(5..20).collect::<IdSet>());
+    assert!(matches!(inline.blocks, store::BlockStore::Stack(_)));
+
+    let collected: IdSet = (0..300_000usize).into_par_iter().filter(|n| n % 3 == 0).collect();
+    assert_eq!(collected, a);
+    assert_eq!(collected.len(), a.len());
+    let mut extended = a.clone();
+    extended.par_extend(b.par_iter());
+    assert_eq!(extended, union);
+    assert_eq!(extended.len(), union.len());
+}
+
+#[test]
+fn from_blocks() {
+    let blocks: Vec<u64> = vec![0b1011, 0, 1 << 63];
+    let ptr = blocks.as_ptr();
+    let set: IdSet<u64> = IdSet::from_blocks(blocks);
+    assert_eq!(set.len(), 4);
+    assert_eq!(set.iter().collect::<Vec<_>>(), [0, 1, 3, 191]);
+
+    let vec = set.into_vec();
+    assert_eq!(vec.as_ptr(), ptr);
+    assert_eq!(vec, [0b1011, 0, 1 << 63]);
+
+    let set: IdSet<u64> = IdSet::from_block_slice(&vec[..1]);
+    assert_eq!(set.len(), 3);
+    assert_eq!(set.as_blocks()[0], 0b1011);
+    assert_eq!(Vec::from(set.clone()).len(), DEFAULT_INLINE);
+
+    let set: IdSet<u64, 0> = IdSet::from(vec.clone());
+    assert_eq!(set.len(), 4);
+    let set = IdSet::<u64, 8>::from_block_slice(&vec);
+    assert_eq!(set.len(), 4);
+    assert_eq!(set.capacity(), 8 * 64);
+    assert_eq!(IdSet::<u64, 8>::from_blocks(vec), set);
+}
+
+#[test]
+fn wire_format() {
+    let set: IdSet<u16> = vec![1, 17, 40].into_iter().chain(500..520).collect();
+    let mut buf = Vec::new();
+    set.write_to(&mut buf).unwrap();
+    assert_eq!(&buf[..6], b"IDST\x01\x02");
+    assert_eq!(&buf[6..14], &23u64.to_le_bytes());
+    assert_eq!(&buf[14..22], &33u64.to_le_bytes());
+    assert_eq!(&buf[22..26], &[0x02, 0x00, 0x02, 0x00]);
+    assert_eq!(buf.len(), 22 + 33 * 2);
+    assert_eq!(IdSet::<u16>::read_from(&mut &buf[..]).unwrap(), set);
+    let inline = IdSet::<u16, 40>::read_from(&mut &buf[..]).unwrap();
+    assert_eq!(inline, set);
+    assert_eq!(inline.len(), set.len());
+
+    let mut empty = Vec::new();
+    IdSet::new().write_to(&mut empty).unwrap();
+    assert_eq!(empty.len(), 22);
+    assert!(IdSet::<Block>::read_from(&mut &empty[..]).unwrap().is_empty());
+
+    match IdSet::<u16>::read_from(&mut &buf[..buf.len() - 1]) {
+        Err(ReadError::Truncated) => {}
+        other => panic!("{:?}", other),
+    }
+    match IdSet::<u32>::read_from(&mut &buf[..]) {
+        Err(ReadError::BlockWidth { expected: 32, found: 16 }) => {}
+        other => panic!("{:?}", other),
+    }
+    buf[6] = 24;
+    match IdSet::<u16>::read_from(&mut &buf[..]) {
+        Err(ReadError::CountMismatch { expected: 24, found: 23 }) => {}
+        other => panic!("{:?}", other),
+    }
+    buf[0] = b'X';
+    match IdSet::<u16>::read_from(&mut &buf[..]) {
+        Err(ReadError::BadMagic) => {}
+        other => panic!("{:?}", other),
+    }
+}
+
+#[test]
+fn sparse() {
+    let mut set = SparseIdSet::new();
+    assert!(set.insert(1_000_000_000));
+    assert!(!set.insert(1_000_000_000));
+    assert!(set.insert(3));
+    assert!(set.contains(3));
+    assert!(!set.contains(4));
+    assert_eq!(set.len(), 2);
+    assert_eq!(set.blocks().into_inner().len(), 1_000_000_000 / 64 + 1);
+    assert_eq!(set.blocks().into_iter().collect::<Vec<_>>(), [3, 1_000_000_000]);
+    let mut blocks = set.blocks().into_inner();
+    assert_eq!(blocks.nth(1_000_000_000 / 64), Some(1 << (1_000_000_000 % 64)));
+    assert_eq!(blocks.nth(usize::MAX), None);
+
+    // Grow a chunk past the array limit into a bitmap and back.
+    set.extend((0..10000).map(|n| n * 2));
+    assert_eq!(set.len(), 10002);
+    assert!(set.contains(19998) && !set.contains(19999));
+    for n in 0..9000 {
+        assert!(set.remove(n * 2));
+    }
+    assert!(!set.remove(0));
+    assert_eq!(set.len(), 1002);
+    assert_eq!(set.iter().next(), Some(3));
+
+    let runs: SparseIdSet = (100..70000).chain(80000..80010).collect();
+    let mut optimized = runs.clone();
+    optimized.run_optimize();
+    assert_eq!(optimized, runs);
+    assert!(optimized.contains(65536) && !optimized.contains(70000));
+    assert!(optimized.remove(500));
+    assert!(optimized.insert(500));
+    assert_eq!(optimized, runs);
+    let dense: IdSet<u64> = runs.iter().collect();
+    assert!(optimized.blocks().into_inner().eq(dense.as_blocks().iter().cloned()));
+    assert!(runs.blocks().into_inner().eq(dense.as_blocks().iter().cloned()));
+
+    let a: SparseIdSet = (0..5000).chain(100_000..100_010).collect();
+    let b: SparseIdSet = (2500..3000).chain(100_005..200_000).collect();
+    let (dense_a, dense_b) = (a.blocks().into_set(), b.blocks().into_set());
+    let check = |sparse: SparseIdSet, dense: Vec<Id>| {
+        assert_eq!(sparse.iter().collect::<Vec<_>>(), dense);
+    };
+    check(&a | &b, dense_a.union(&dense_b).collect());
+    check(&a & &b, dense_a.intersection(&dense_b).collect());
+    check(&a - &b, dense_a.difference(&dense_b).collect());
+    check(&a ^ &b, dense_a.symmetric_difference(&dense_b).collect());
+    check(&optimized & &b, runs.blocks().intersection(&dense_b).collect());
+    assert!(a.is_subset(&(&a | &b)) && !a.is_subset(&b));
+    assert!(a.is_disjoint(&(&b - &a)));
+
+    let mut c = a.clone();
+    c |= &b;
+    c -= &a;
+    assert_eq!(c, &b - &a);
+    assert_eq!(SparseIdSet::from_blocks(&dense_a), a);
+}
+
+#[test]
+fn interval() {
+    let mut set = IntervalIdSet::new();
+    assert_eq!(set.insert_range(10..20), 10);
+    assert_eq!(set.insert_range(30..40), 10);
+    assert_eq!(set.insert_range(15..35), 10);
+    assert_eq!((set.iter_ranges().next(), set.iter_ranges().len()), (Some(10..=39), 1));
+    assert!(set.insert(40));
+    assert!(!set.insert(40));
+    assert!(set.insert(50));
+    assert_eq!(set.remove_range(20..=22), 3);
+    assert!(set.remove(10));
+    assert!(!set.remove(10));
+    assert_eq!(set.iter_ranges().collect::<Vec<_>>(), [11..=19, 23..=40, 50..=50]);
+    assert_eq!(set.len(), 28);
+    assert_eq!(set.iter().len(), 28);
+    assert!(set.contains(11) && set.contains(40) && !set.contains(41) && !set.contains(22));
+    assert!(set.contains_range(23..41) && !set.contains_range(19..24));
+    assert!(set.intersects_range(41..) && !set.intersects_range(41..50));
+    assert_eq!((set.first(), set.last()), (Some(11), Some(50)));
+
+    let dense = IdSet::<u16>::from(&set);
+    assert_eq!(dense.iter().collect::<Vec<_>>(), set.iter().collect::<Vec<_>>());
+    assert_eq!(IntervalIdSet::from(&dense), set);
+    assert_eq!(set.blocks::<u8>().into_set().iter().collect::<Vec<_>>(),
+               set.iter().collect::<Vec<_>>());
+    assert_eq!((&IdSet::new() | &set).collect::<Vec<_>>(), set.iter().collect::<Vec<_>>());
+
+    let a: IntervalIdSet = (0..100).filter(|n| n % 7 < 4).collect();
+    let b: IntervalIdSet = (0..120).filter(|n| n % 5 < 3).collect();
+    let (dense_a, dense_b): (IdSet, IdSet) = ((&a).into(), (&b).into());
+    let check = |interval: IntervalIdSet, dense: Vec<Id>| {
+        assert_eq!(interval.iter().collect::<Vec<_>>(), dense);
+        assert_eq!(interval.len(), dense.len());
+    };
+    check(&a | &b, dense_a.union(&dense_b).collect());
+    check(&a & &b, dense_a.intersection(&dense_b).collect());
+    check(&a - &b, dense_a.difference(&dense_b).collect());
+    check(&a ^ &b, dense_a.symmetric_difference(&dense_b).collect());
+    assert!(a.is_subset(&(&a | &b)) && !a.is_subset(&b));
+    assert!(a.is_disjoint(&(&b - &a)));
+
+    let mut set = IntervalIdSet::new();
+    assert!(set.insert(usize::MAX) && !set.insert(usize::MAX));
+    assert_eq!(set.insert_range(usize::MAX - 3..usize::MAX), 3);
+    assert_eq!(set.iter_ranges().collect::<Vec<_>>(), [usize::MAX - 3..=usize::MAX]);
+    assert!(set.contains(usize::MAX) && set.contains_range(usize::MAX - 3..=usize::MAX));
+    assert!(set.intersects_range(usize::MAX..));
+    assert_eq!(set.iter().collect::<Vec<_>>(), (usize::MAX - 3..=usize::MAX).collect::<Vec<_>>());
+    let other: IntervalIdSet = vec![0, usize::MAX - 1].into_iter().collect();
+    assert_eq!((&set ^ &other).iter().collect::<Vec<_>>(),
+               [0, usize::MAX - 3, usize::MAX - 2, usize::MAX]);
+    assert_eq!((&set & &other).iter().collect::<Vec<_>>(), [usize::MAX - 1]);
+    assert_eq!((&set | &other).len(), 5);
+    assert!(set.remove(usize::MAX) && !set.remove(usize::MAX));
+    assert_eq!(set.remove_range(usize::MAX - 2..), 2);
+    assert_eq!((set.len(), set.last()), (1, Some(usize::MAX - 3)));
+}
+
+#[test]
+fn ranges_iter() {
+    let runs = vec![0..1, 3..8, 31..33, 40..200, 255..256, 300..301];
+    let mut set = IdSet::new();
+    for range in &runs {
+        set.insert_range(range.clone());
+    }
+    assert_eq!(set.ranges().collect::<Vec<_>>(), runs);
+    assert_eq!(IdSet::new().ranges().count(), 0);
+    assert_eq!(IdSet::new_filled(64).ranges().collect::<Vec<_>>(), vec![0..64]);
+
+    let other: IdSet = (100..400).collect();
+    assert_eq!(set.intersection(&other).ranges().collect::<Vec<_>>(),
+               [100..200, 255..256, 300..301]);
+    assert_eq!(set.union(&other).ranges().collect::<Vec<_>>(), [0..1, 3..8, 31..33, 40..400]);
+
+    for n in 0..20 {
+        let set: IdSet<u8> = (0..100).filter(|m| (m / (n + 1)) % 2 == 0).collect();
+        let mut expected = Vec::new();
+        for id in set.iter() {
+            match expected.last_mut() {
+                Some(&mut ops::Range { ref mut end, .. }) if *end == id => *end += 1,
+                _ => expected.push(id..id + 1),
+            }
+        }
+        assert_eq!(set.ranges().collect::<Vec<_>>(), expected);
+    }
+}
+
+#[test]
+fn combination_len() {
+    let a: IdSet = (0..300).filter(|n| n % 3 == 0).collect();
+    let b: IdSet<Block, 0> = (0..500).filter(|n| n % 5 == 0).collect();
+    assert_eq!(a.intersection_len(&b), a.intersection(&b).into_iter().count());
+    assert_eq!(a.union_len(&b), a.union(&b).into_iter().count());
+    assert_eq!(a.difference_len(&b), a.difference(&b).into_iter().count());
+    assert_eq!(b.difference_len(&a), b.difference(&a).into_iter().count());
+    assert_eq!(a.symmetric_difference_len(&b),
+               a.symmetric_difference(&b).into_iter().count());
+    assert_eq!(a.intersection_len(&IdSet::new()), 0);
+
+    let c: IdSet = (250..260).collect();
+    assert_eq!((&a | &b | &c).count_ones(), (&a | &b | &c).into_iter().count());
+    assert_eq!(((&a - &b) & &c).count_ones(), 2);
+}
+
+#[test]
+fn similarity() {
+    let a: IdSet = (0..100).collect();
+    let b: IdSet<Block, 0> = (50..250).collect();
+    assert_eq!(jaccard(&a, &b), 50.0 / 250.0);
+    assert_eq!(dice(&a, &b), 100.0 / 300.0);
+    assert_eq!(overlap_coefficient(&a, &b), 0.5);
+    assert_eq!(hamming_distance(&a, &b), 200);
+    assert_eq!(hamming_distance(&b, &a), a.symmetric_difference_len(&b));
+
+    let c: IdSet = (90..95).collect();
+    assert_eq!(overlap_coefficient(&c, &a), 1.0);
+    assert_eq!(jaccard(a.intersection(&b), &b), 0.25);
+    assert_eq!(jaccard(&IdSet::new(), &IdSet::new()), 1.0);
+    assert_eq!(jaccard(&a, &IdSet::new()), 0.0);
+    assert_eq!(hamming_distance(&a, &a), 0);
+}
+
+#[test]
+fn simd_ops() {
+    use simd::{apply, apply_words, count, count_words, Op};
+
+    let eval = |op, l: u8, r: u8| match op {
+        Op::And => l & r,
+        Op::Or => l | r,
+        Op::Xor => l ^ r,
+        Op::AndNot => l & !r,
+    };
+    let lhs: Vec<u8> = (0..300u32).map(|n| (n * 151 % 256) as u8).collect();
+    let rhs: Vec<u8> = (0..300u32).map(|n| (n * n % 251) as u8).collect();
+    for &op in &[Op::And, Op::Or, Op::Xor, Op::AndNot] {
+        for &len in &[0, 1, 7, 8, 31, 32, 33, 100, 300] {
+            let (lhs, rhs) = (&lhs[..len], &rhs[..len]);
+            let expected: Vec<u8> = lhs.iter().zip(rhs).map(|(&l, &r)| eval(op, l, r)).collect();
+            let ones = expected.iter().map(|block| block.count_ones() as usize).sum::<usize>();
+            let mut result = lhs.to_vec();
+            assert_eq!(apply(&mut result, rhs, op), ones);
+            assert_eq!(result, expected);
+            let mut result = lhs.to_vec();
+            assert_eq!(apply_words(&mut result, rhs, op), ones);
+            assert_eq!(result, expected);
+            assert_eq!(count(lhs, rhs, op), ones);
+            assert_eq!(count_words(lhs, rhs, op), ones);
+        }
+    }
+}
+
+#[test]
+fn assign_slices() {
+    let sets: Vec<IdSet<u64, 2>> = vec![IdSet::new_general(),
+                                        (0..100).collect(),
+                                        (50..1000).filter(|n| n % 3 == 0).collect(),
+                                        (0..5000).filter(|n| n % 7 != 0).collect()];
+    for a in &sets {
+        for b in &sets {
+            // Block iterators never take the slice path.
+            let check = |slice: IdSet<u64, 2>, blocks: IdSet<u64, 2>| {
+                assert_eq!(slice, blocks);
+                assert_eq!(slice.len(), slice.iter().count());
+            };
+            let (mut slice, mut blocks) = (a.clone(), a.clone());
+            slice &= b;
+            blocks &= b.blocks();
+            check(slice, blocks);
+
+            let (mut slice, mut blocks) = (a.clone(), a.clone());
+            slice |= b;
+            blocks |= b.blocks();
+            check(slice, blocks);
+
+            let (mut slice, mut blocks) = (a.clone(), a.clone());
+            slice ^= b;
+            blocks ^= b.blocks();
+            check(slice, blocks);
+
+            let (mut slice, mut blocks) = (a.clone(), a.clone());
+            slice -= b.clone();
+            blocks -= b.blocks();
+            check(slice, blocks);
+        }
+    }
+
+    // Sets stay inline unless the other set has more blocks.
+    let is_inline = |set: &IdSet| matches!(set.blocks, store::BlockStore::Stack(_));
+    let mut a: IdSet = (0..10).collect();
+    let b: IdSet = (5..20).collect();
+    a |= &b;
+    a ^= &b;
+    assert!(is_inline(&a));
+    a |= &(0..1000).collect::<IdSet>();
+    assert!(!is_inline(&a));
+}
+
+#[test]
+fn multi_way() {
+    let sets: Vec<IdSet> = (1..8).map(|n| (0..n * 40).filter(|m| m % n == 0).collect()).collect();
+
+    let mut union = IdSet::new();
+    let mut intersection = sets[0].clone();
+    for set in &sets {
+        union |= set;
+        intersection &= set;
+    }
+    assert_eq!(IdSet::union_all(&sets).into_set(), union);
+    assert_eq!(IdSet::intersection_all(&sets).into_set(), intersection);
+    assert_eq!(IdSet::union_all(&sets).into_inner().len(), sets[6].blocks().len());
+    assert_eq!(IdSet::intersection_all(&sets).into_inner().len(), sets[0].blocks().len());
+    assert!(IdSet::union_all(Vec::<&IdSet>::new()).into_set().is_empty());
+    assert!(IdSet::intersection_all(Vec::<&IdSet>::new()).into_set().is_empty());
+
+    for k in 1..9 {
+        let expected: Vec<Id> = (0..400)
+            .filter(|&id| sets.iter().filter(|set| set.contains(id)).count() >= k)
+            .collect();
+        assert_eq!(IdSet::threshold(&sets, k).collect::<Vec<_>>(), expected);
+    }
+    assert_eq!(IdSet::threshold(&sets, 1).into_set(), union);
+    assert_eq!(IdSet::threshold(&sets, 7).into_set(), intersection);
+    assert_eq!(IdSet::threshold(vec![sets[0].blocks(), sets[1].blocks()], 2).into_set(),
+               sets[0].intersection(&sets[1]).into_set());
+}
+
+#[test]
+fn dyn_block_iter() {
+    let a: IdSet = (0..100).filter(|n| n % 2 == 0).collect();
+    let b: IdSet = (0..300).filter(|n| n % 3 == 0).collect();
+    let c: IdSet = (50..60).collect();
+
+    // Builds ((a & b) | c) ^ a one operation at a time.
+    let ops: Vec<(char, &IdSet)> = vec![('&', &b), ('|', &c), ('^', &a)];
+    let mut expr = (&a).into_dyn();
+    for &(op, set) in &ops {
+        expr = match op {
+            '&' => (expr & set).into_dyn(),
+            '|' => (expr | set).into_dyn(),
+            '^' => (expr ^ set).into_dyn(),
+            _ => (expr - set).into_dyn(),
+        };
+    }
+    let expected = ((&a & &b) | &c) ^ &a;
+    assert_eq!(expr.into_inner().len(), expected.clone().into_inner().len());
+    let expr = (((&a).into_dyn() & &b).into_dyn() | &c).into_dyn() ^ (&a).into_dyn();
+    assert_eq!(expr.collect::<Vec<_>>(), expected.collect::<Vec<_>>());
+
+    let mut set = a.clone();
+    set -= DynBlockIter::empty();
+    set |= b.clone().into_dyn();
+    assert_eq!(set, (&a | &b).into_set());
+    assert!(DynBlockIter::<u8>::empty().into_set().is_empty());
+}
+
+#[test]
+fn expressions() {
+    use expr::{EvalError, Expr, ParseErrorKind};
+
+    let name = |name: &str| Box::new(Expr::Name(name.to_owned()));
+    let not_d = Box::new(Expr::Not(name("d")));
+    let sub = Expr::Sub(Box::new(Expr::And(vec![*name("c"), *not_d])), name("e"));
+    assert_eq!(Expr::parse("a | b ^ c & !d - e").unwrap(),
+               Expr::Or(vec![*name("a"), Expr::Xor(vec![*name("b"), sub])]));
+    assert_eq!(Expr::parse(" ( tags:a.b_1 ) ").unwrap(), *name("tags:a.b_1"));
+    assert_eq!(Expr::parse("!!a").unwrap(), Expr::Not(Box::new(Expr::Not(name("a")))));
+    assert_eq!(Expr::parse("a - b & c - d").unwrap(),
+               Expr::Sub(Box::new(Expr::And(vec![*name("a"), *name("c")])),
+                         Box::new(Expr::Or(vec![*name("b"), *name("d")]))));
+    assert_eq!(Expr::parse("a | (b | c)").unwrap(),
+               Expr::Or(vec![*name("a"), Expr::Or(vec![*name("b"), *name("c")])]));
+
+    let check_err = |input: &str, kind: ParseErrorKind, position: usize| {
+        let err = Expr::parse(input).unwrap_err();
+        assert_eq!((err.kind(), err.position()), (kind, position));
+    };
+    check_err("", ParseErrorKind::UnexpectedEnd, 0);
+    check_err("a &", ParseErrorKind::UnexpectedEnd, 3);
+    check_err("a b", ParseErrorKind::UnexpectedChar('b'), 2);
+    check_err("a & (b | c", ParseErrorKind::UnclosedParen, 4);
+    check_err("(a b)", ParseErrorKind::UnexpectedChar('b'), 3);
+    check_err("a)", ParseErrorKind::UnexpectedChar(')'), 1);
+    check_err("a | * b", ParseErrorKind::UnexpectedChar('*'), 4);
+    let deep = "(".repeat(200000) + "a" + &")".repeat(200000);
+    check_err(&deep, ParseErrorKind::TooDeep, 256);
+    check_err(&"!".repeat(200000), ParseErrorKind::TooDeep, 256);
+    assert!(Expr::parse(&("(".repeat(256) + "a" + &")".repeat(256))).is_ok());
+
+    let a: IdSet<u8> = (0..40).filter(|n| n % 2 == 0).collect();
+    let b: IdSet<u8> = (0..40).filter(|n| n % 3 == 0).collect();
+    let resolve = |name: &str| match name {
+        "a" => Some(&a),
+        "b" => Some(&b),
+        _ => None,
+    };
+    let eval = |input: &str, universe| {
+        Expr::parse(input).unwrap().eval(resolve, universe).map(|iter| iter.collect::<Vec<_>>())
+    };
+    assert_eq!(eval("a & b", 0).unwrap(), a.intersection(&b).collect::<Vec<_>>());
+    assert_eq!(eval("(a | b) - (a ^ b)", 0).unwrap(), eval("a & b", 0).unwrap());
+    assert_eq!(eval("!a", 13).unwrap(), [1, 3, 5, 7, 9, 11]);
+    assert_eq!(eval("!(a | b)", 20).unwrap(), [1, 5, 7, 11, 13, 17, 19]);
+    assert_eq!(eval("!a & !b", 20).unwrap(), eval("!(a | b)", 20).unwrap());
+    assert_eq!(eval("!a", 0).unwrap(), Vec::<Id>::new());
+    assert_eq!(eval("a | c", 0), Err(EvalError::UnknownName("c".to_owned())));
+
+    let nested = "(".repeat(128) + &"!a ^ ".repeat(127) + "a" + &")".repeat(128);
+    assert_eq!(eval(&nested, 40).unwrap(), (0..40).collect::<Vec<_>>());
+
+    let names = |op: &str, n: usize| {
+        (0..n).map(|i| if i % 2 == 0 { "a" } else { "b" }).collect::<Vec<_>>().join(op)
+    };
+    let union = names(" | ", 10000);
+    assert_eq!(Expr::parse(&union).unwrap(),
+               Expr::Or((0..10000).map(|i| *name(if i % 2 == 0 { "a" } else { "b" })).collect()));
+    assert_eq!(eval(&union, 0).unwrap(), eval("a | b", 0).unwrap());
+    assert_eq!(eval(&names(" & ", 10000), 0).unwrap(), eval("a & b", 0).unwrap());
+    assert_eq!(eval(&names(" ^ ", 10000), 0).unwrap(), Vec::<Id>::new());
+    assert_eq!(eval(&names(" ^ ", 10001), 0).unwrap(), eval("a", 0).unwrap());
+    assert_eq!(eval(&("a".to_owned() + &" - b".repeat(9999)), 0).unwrap(),
+               eval("a - b", 0).unwrap());
+}
+
+#[test]
+fn complement() {
+    let set: IdSet<u8, 2> = vec![0, 3, 9, 15, 40].into_iter().collect();
+    for universe in 0..50 {
+        let expected: Vec<Id> = (0..universe).filter(|&id| !set.contains(id)).collect();
+        assert_eq!(set.complement(universe).collect::<Vec<_>>(), expected);
+        assert_eq!(set.complement(universe).count_ones(), expected.len());
+        assert_eq!((!(&set | &IdSet::<u8, 0>::new_general()).within(universe)).collect::<Vec<_>>(),
+                   expected);
+
+        let mut inverted = set.clone();
+        inverted.invert(universe);
+        assert_eq!(inverted.len(), expected.len());
+        assert_eq!(inverted.iter().collect::<Vec<_>>(), expected);
+        inverted.invert(universe);
+        assert_eq!(inverted.iter().collect::<Vec<_>>(),
+                   set.iter().filter(|&id| id < universe).collect::<Vec<_>>());
+    }
+
+    let mut empty = IdSet::new();
+    empty.invert(100);
+    assert_eq!(empty, IdSet::new_filled(100));
+    assert_eq!(IdSet::new_filled(64).complement(64).count_ones(), 0);
+}
+
+#[test]
+fn atomic() {
+    use std::thread;
+
+    let set = AtomicIdSet::<u64>::new_general(1000);
+    assert_eq!(set.capacity(), 1024);
+    assert!(set.insert(5));
+    assert!(!set.insert(5));
+    assert!(set.contains(5) && !set.contains(6) && !set.contains(5000));
+    assert!(set.remove(5));
+    assert!(!set.remove(5) && !set.remove(5000));
+    assert!(set.is_empty());
+
+    thread::scope(|scope| {
+        for n in 0..4 {
+            let set = &set;
+            scope.spawn(move || {
+                for id in (n..1000).step_by(4) {
+                    set.insert(id);
+                }
+                for id in (n..1000).step_by(4).filter(|id| id % 5 == 0) {
+                    set.remove(id);
+                }
+            });
+        }
+    });
+    let expected: IdSet<u64> = (0..1000).filter(|id| id % 5 != 0).collect();
+    assert_eq!(set.len(), expected.len());
+    assert_eq!(set.snapshot(), expected);
+    assert_eq!(set.snapshot().intersection_len(&expected), expected.len());
+
+    let mut set = AtomicIdSet::from(set.into_set());
+    assert_eq!(set.len(), expected.len());
+    set.clear();
+    assert!(set.is_empty() && set.snapshot().is_empty());
+
+    // A removal may update the count before the insertion it undid, but the count must never
+    // appear to wrap around.
+    let set = AtomicIdSet::new(10);
+    thread::scope(|scope| {
+        scope.spawn(|| for _ in 0..100_000 {
+            set.insert(7);
+        });
+        scope.spawn(|| for _ in 0..100_000 {
+            set.remove(7);
+        });
+        scope.spawn(|| for _ in 0..100_000 {
+            assert!(set.len() <= 1);
+        });
+    });
+    assert_eq!(set.len(), set.contains(7) as usize);
+}
+
+#[test]
+fn typed() {
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    struct UserId(usize);
+
+    impl IdLike for UserId {
+        fn to_index(self) -> Id {
+            self.0
+        }
+
+        fn from_index(index: Id) -> Self {
+            UserId(index)
+        }
+    }
+
+    let a: TypedIdSet<UserId> = (0..10).map(UserId).collect();
+    let mut b = TypedIdSet::<UserId, u32, 0>::new_general();
+    assert!(b.insert(UserId(5)));
+    assert!(!b.insert(UserId(5)));
+    b.extend(vec![UserId(8), UserId(100)]);
+    assert!(b.contains(UserId(100)) && !b.contains(UserId(99)));
+    assert_eq!(b.first(), Some(UserId(5)));
+    assert_eq!(b.last(), Some(UserId(100)));
+    assert_eq!(format!("{:?}", b), "{UserId(5), UserId(8), UserId(100)}");
+
+    assert_eq!(a.intersection(&b).collect::<Vec<_>>(), [UserId(5), UserId(8)]);
+    assert_eq!((&a - &b).count_ones(), 8);
+    assert_eq!(a.union(&b).into_set().len(), 11);
+    assert_eq!((&a ^ &b).into_iter().next_back(), Some(UserId(100)));
+    assert_eq!(((&a | &b) - &a).into_set(), (&b - &a).into_set());
+    assert!(!a.is_disjoint(&b) && !a.is_subset(&b));
+
+    let mut c = a.clone();
+    c -= &b;
+    c.inplace_union(&b);
+    assert_eq!(c, a.union(&b).into_set());
+    c &= a.intersection(&b);
+    assert_eq!(c.iter().collect::<Vec<_>>(), [UserId(5), UserId(8)]);
+    assert_eq!(c.as_untyped(), &b.as_untyped().intersection(a.as_untyped()).into_set());
+    assert_eq!(c.into_iter().len(), 2);
+}
+
+#[test]
+fn id_map() {
+    use std::rc::Rc;
+
+    let mut map = IdMap::new();
+    assert_eq!(map.insert(3, "a".to_owned()), None);
+    assert_eq!(map.insert(40, "b".to_owned()), None);
+    assert_eq!(map.insert(3, "c".to_owned()), Some("a".to_owned()));
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get(3).map(String::as_str), Some("c"));
+    assert_eq!(map.get(4), None);
+    assert_eq!(map.get(1000), None);
+    map.entry(40).or_default().push('!');
+    map.entry(7).or_insert_with(|| "d".to_owned());
+    map.entry(7).and_modify(|value| value.push('?')).or_default();
+    match map.entry(5) {
+        Entry::Vacant(entry) => assert_eq!(entry.key(), 5),
+        Entry::Occupied(_) => panic!(),
+    }
+    if let Entry::Occupied(entry) = map.entry(3) {
+        assert_eq!(entry.remove(), "c");
+    }
+    assert_eq!(map.iter().map(|(id, value)| (id, value.as_str())).collect::<Vec<_>>(),
+               [(7, "d?"), (40, "b!")]);
+    for (id, value) in &mut map {
+        value.push_str(&id.to_string());
+    }
+    assert_eq!(map[40], "b!40");
+    assert_eq!(map.iter_mut().next_back().map(|(id, _)| id), Some(40));
+    assert_eq!(map.remove(7), Some("d?7".to_owned()));
+    assert_eq!(map.remove(7), None);
+    assert_eq!(format!("{:?}", map), r#"{40: "b!40"}"#);
+
+    let value = Rc::new(());
+    let mut map: IdMap<_> = (0..100).map(|id| (id * 3, value.clone())).collect();
+    assert_eq!(Rc::strong_count(&value), 101);
+    map.retain(|id, _| id % 2 == 0);
+    assert_eq!(Rc::strong_count(&value), 51);
+    assert_eq!(map.keys(), &(0..300).step_by(6).collect::<IdSet>());
+    let evens: IdSet = (0..300).step_by(2).collect();
+    assert_eq!((map.keys() - &evens).count_ones(), 0);
+    let clone = map.clone();
+    assert_eq!(clone, map);
+    assert_eq!(Rc::strong_count(&value), 101);
+    drop(clone);
+    let mut iter = map.into_iter();
+    assert_eq!(iter.next().map(|(id, _)| id), Some(0));
+    assert_eq!(iter.next_back().map(|(id, _)| id), Some(294));
+    assert_eq!(iter.len(), 48);
+    drop(iter);
+    assert_eq!(Rc::strong_count(&value), 1);
+}
+
+#[test]
+fn bit_matrix() {
+    let (n, m) = (37, 21);
+    let mut a = BitMatrix::<u8>::new_general(n, m);
+    let mut b = BitMatrix::<u8>::new_general(m, n);
+    for i in 0..n {
+        for j in 0..m {
+            if (i * 7 + j * 13) % 11 == 0 {
+                a.insert(i, j);
+            }
+            if (i * 5 + j * 3) % 17 == 0 {
+                b.insert(j, i);
+            }
+        }
+    }
+
+    let transpose = a.transpose();
+    assert_eq!((transpose.num_rows(), transpose.num_cols()), (m, n));
+    assert_eq!(transpose.transpose(), a);
+    for j in 0..m {
+        assert_eq!(a.column(j), *transpose.row(j));
+        assert_eq!(a.column(j), (0..n).filter(|&i| a.contains(i, j)).collect::<IdSet<u8>>());
+    }
+
+    let product = a.product(&b);
+    assert_eq!((product.num_rows(), product.num_cols()), (n, n));
+    for i in 0..n {
+        for j in 0..n {
+            assert_eq!(product.contains(i, j),
+                       (0..m).any(|k| a.contains(i, k) && b.contains(k, j)));
+        }
+    }
+
+    let closure = product.transitive_closure();
+    let mut expected = product.clone();
+    for _ in 0..n {
+        let step = expected.product(&product);
+        let rows = expected.rows().iter().zip(step.rows()).map(|(l, r)| l.union(r).into_set());
+        expected = BitMatrix::from_rows(rows.collect(), n);
+    }
+    assert_eq!(closure, expected);
+
+    assert_eq!(a.row_union(vec![0, 11]).into_set(), a.row(0).union(a.row(11)).into_set());
+    assert_eq!(a.row_union(None).count_ones(), 0);
+    assert!(a.remove(0, 0) && !a.contains(0, 0));
+}
@@ -0,0 +1,597 @@
+use std::{fmt, ops};
+use std::iter::FromIterator;
+use std::marker::PhantomData;
+
+use super::{BitBlock, Block, BlockIter, Blocks, Difference, Id, IdIter, IdSet, Intersection,
+            IntoBlockIterator, IntoBlocks, SymmetricDifference, Union, DEFAULT_INLINE};
+
+/// A type which can be used as the element type of a `TypedIdSet`, typically a newtype around an
+/// index.
+///
+/// `from_index(id.to_index())` must return a value equal to `id`.
+pub trait IdLike: Copy {
+    /// Converts the value into an index.
+    fn to_index(self) -> Id;
+
+    /// Converts an index back into a value.
+    fn from_index(index: Id) -> Self;
+}
+
+impl IdLike for Id {
+    #[inline]
+    fn to_index(self) -> Id {
+        self
+    }
+
+    #[inline]
+    fn from_index(index: Id) -> Self {
+        index
+    }
+}
+
+/// An `IdSet` of values of type `I`, which can only be combined with sets and block iterators of
+/// the same type.
+///
+/// This is a thin wrapper around an `IdSet`, converting elements with [`IdLike`]. The untyped set
+/// can be accessed with [`as_untyped`](#method.as_untyped).
+///
+/// # Examples
+///
+/// ```
+/// # use id_set::{IdLike, TypedIdSet};
+/// #
+/// #[derive(Copy, Clone, Debug, PartialEq)]
+/// struct UserId(usize);
+///
+/// impl IdLike for UserId {
+///     fn to_index(self) -> usize { self.0 }
+///     fn from_index(index: usize) -> Self { UserId(index) }
+/// }
+///
+/// let admins: TypedIdSet<UserId> = vec![UserId(1), UserId(4)].into_iter().collect();
+/// let mut active = TypedIdSet::new();
+/// active.insert(UserId(4));
+/// active.insert(UserId(7));
+/// assert_eq!((&admins & &active).collect::<Vec<_>>(), [UserId(4)]);
+/// ```
+///
+/// Sets of different types cannot be combined.
+///
+/// ```compile_fail
+/// # use id_set::{IdLike, TypedIdSet};
+/// #
+/// # #[derive(Copy, Clone)]
+/// # struct UserId(usize);
+/// # impl IdLike for UserId {
+/// #     fn to_index(self) -> usize { self.0 }
+/// #     fn from_index(index: usize) -> Self { UserId(index) }
+/// # }
+/// # #[derive(Copy, Clone)]
+/// # struct OrderId(usize);
+/// # impl IdLike for OrderId {
+/// #     fn to_index(self) -> usize { self.0 }
+/// #     fn from_index(index: usize) -> Self { OrderId(index) }
+/// # }
+/// let users = TypedIdSet::<UserId>::new();
+/// let orders = TypedIdSet::<OrderId>::new();
+/// let both = &users & &orders;
+/// ```
+///
+/// [`IdLike`]: trait.IdLike.html
+pub struct TypedIdSet<I, T: BitBlock = Block, const N: usize = DEFAULT_INLINE> {
+    set: IdSet<T, N>,
+    marker: PhantomData<fn(I) -> I>,
+}
+
+impl<I: IdLike> TypedIdSet<I> {
+    #[inline]
+    /// Creates an empty `TypedIdSet`.
+    pub fn new() -> Self {
+        TypedIdSet::from_untyped(IdSet::new())
+    }
+
+    #[inline]
+    /// Creates a empty `TypedIdSet` that can hold elements with indices up to n before
+    /// reallocating.
+    pub fn with_capacity(n: usize) -> Self {
+        TypedIdSet::from_untyped(IdSet::with_capacity(n))
+    }
+}
+
+impl<I: IdLike, T: BitBlock, const N: usize> TypedIdSet<I, T, N> {
+    #[inline]
+    /// Creates an empty `TypedIdSet` with any block type and inline size.
+    pub fn new_general() -> Self {
+        TypedIdSet::from_untyped(IdSet::new_general())
+    }
+
+    #[inline]
+    /// Creates a `TypedIdSet` holding the elements whose indices are in the given set.
+    pub fn from_untyped(set: IdSet<T, N>) -> Self {
+        TypedIdSet {
+            set,
+            marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    /// Returns the underlying set of indices.
+    pub fn as_untyped(&self) -> &IdSet<T, N> {
+        &self.set
+    }
+
+    #[inline]
+    /// Consumes the set and returns the underlying set of indices.
+    pub fn into_untyped(self) -> IdSet<T, N> {
+        self.set
+    }
+
+    #[inline]
+    /// Returns the number of distinct elements in the set.
+    pub fn len(&self) -> usize {
+        self.set.len()
+    }
+
+    #[inline]
+    /// Returns true if the set is empty.
+    pub fn is_empty(&self) -> bool {
+        self.set.is_empty()
+    }
+
+    #[inline]
+    /// Returns capacity of the set. Inserting elements with indices larger than this will cause
+    /// reallocation.
+    pub fn capacity(&self) -> usize {
+        self.set.capacity()
+    }
+
+    #[inline]
+    /// Removes all elements from the set.
+    pub fn clear(&mut self) {
+        self.set.clear()
+    }
+
+    #[inline]
+    /// Inserts the given element into the set, returning true if it was not already in the set.
+    pub fn insert(&mut self, id: I) -> bool {
+        self.set.insert(id.to_index())
+    }
+
+    #[inline]
+    /// Removes the given element from the set, returning true if it was in the set.
+    pub fn remove(&mut self, id: I) -> bool {
+        self.set.remove(id.to_index())
+    }
+
+    #[inline]
+    /// Returns true if the given element is in the set.
+    pub fn contains(&self, id: I) -> bool {
+        self.set.contains(id.to_index())
+    }
+
+    #[inline]
+    /// Returns the element with the smallest index, or `None` if the set is empty.
+    pub fn first(&self) -> Option<I> {
+        self.set.first().map(I::from_index)
+    }
+
+    #[inline]
+    /// Returns the element with the largest index, or `None` if the set is empty.
+    pub fn last(&self) -> Option<I> {
+        self.set.last().map(I::from_index)
+    }
+
+    #[inline]
+    /// Returns an iterator over all elements in increasing order of index.
+    pub fn iter(&self) -> Iter<I, super::Iter<'_, T>> {
+        Iter::new(self.set.iter())
+    }
+
+    #[inline]
+    /// Takes the union of the set with another. Equivalent to `self | other`.
+    pub fn union<R>(&self, other: R) -> TypedBlockIter<I, Union<Blocks<'_, T>, UntypedBlocks<R>>>
+        where R: IntoTypedBlockIterator<Id = I>,
+              R::Untyped: IntoBlockIterator<Block = T>
+    {
+        self | other
+    }
+
+    #[inline]
+    /// Takes the intersection of the set with another. Equivalent to `self & other`.
+    pub fn intersection<R>(&self,
+                           other: R)
+                           -> TypedBlockIter<I, Intersection<Blocks<'_, T>, UntypedBlocks<R>>>
+        where R: IntoTypedBlockIterator<Id = I>,
+              R::Untyped: IntoBlockIterator<Block = T>
+    {
+        self & other
+    }
+
+    #[inline]
+    /// Takes the difference of the set with another. Equivalent to `self - other`.
+    pub fn difference<R>(&self,
+                         other: R)
+                         -> TypedBlockIter<I, Difference<Blocks<'_, T>, UntypedBlocks<R>>>
+        where R: IntoTypedBlockIterator<Id = I>,
+              R::Untyped: IntoBlockIterator<Block = T>
+    {
+        self - other
+    }
+
+    #[inline]
+    /// Takes the symmetric difference of the set with another. Equivalent to `self ^ other`.
+    pub fn symmetric_difference<R>
+        (&self,
+         other: R)
+         -> TypedBlockIter<I, SymmetricDifference<Blocks<'_, T>, UntypedBlocks<R>>>
+        where R: IntoTypedBlockIterator<Id = I>,
+              R::Untyped: IntoBlockIterator<Block = T>
+    {
+        self ^ other
+    }
+
+    #[inline]
+    /// Take the union of the set inplace with another set. Equivalent to `*self |= other`.
+    pub fn inplace_union<R>(&mut self, other: R)
+        where R: IntoTypedBlockIterator<Id = I>,
+              R::Untyped: IntoBlockIterator<Block = T>
+    {
+        *self |= other
+    }
+
+    #[inline]
+    /// Take the intersection of the set inplace with another set. Equivalent to `*self &= other`.
+    pub fn inplace_intersection<R>(&mut self, other: R)
+        where R: IntoTypedBlockIterator<Id = I>,
+              R::Untyped: IntoBlockIterator<Block = T>
+    {
+        *self &= other
+    }
+
+    #[inline]
+    /// Take the difference of the set inplace with another set. Equivalent to `*self -= other`.
+    pub fn inplace_difference<R>(&mut self, other: R)
+        where R: IntoTypedBlockIterator<Id = I>,
+              R::Untyped: IntoBlockIterator<Block = T>
+    {
+        *self -= other
+    }
+
+    #[inline]
+    /// Take the symmetric difference of the set inplace with another set. Equivalent to
+    /// `*self ^= other`.
+    pub fn inplace_symmetric_difference<R>(&mut self, other: R)
+        where R: IntoTypedBlockIterator<Id = I>,
+              R::Untyped: IntoBlockIterator<Block = T>
+    {
+        *self ^= other
+    }
+
+    #[inline]
+    /// Returns true if the sets are disjoint.
+    pub fn is_disjoint<const M: usize>(&self, other: &TypedIdSet<I, T, M>) -> bool {
+        self.set.is_disjoint(&other.set)
+    }
+
+    #[inline]
+    /// Returns true if self is a superset of other.
+    pub fn is_superset<const M: usize>(&self, other: &TypedIdSet<I, T, M>) -> bool {
+        self.set.is_superset(&other.set)
+    }
+
+    #[inline]
+    /// Returns true if self is a subset of other.
+    pub fn is_subset<const M: usize>(&self, other: &TypedIdSet<I, T, M>) -> bool {
+        self.set.is_subset(&other.set)
+    }
+}
+
+impl<I, T: BitBlock, const N: usize> Clone for TypedIdSet<I, T, N> {
+    #[inline]
+    fn clone(&self) -> Self {
+        TypedIdSet {
+            set: self.set.clone(),
+            marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    fn clone_from(&mut self, source: &Self) {
+        self.set.clone_from(&source.set);
+    }
+}
+
+impl<I, T, const N: usize> fmt::Debug for TypedIdSet<I, T, N>
+    where I: IdLike + fmt::Debug,
+          T: BitBlock
+{
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_set().entries(self.iter()).finish()
+    }
+}
+
+impl<I: IdLike, T: BitBlock, const N: usize> Default for TypedIdSet<I, T, N> {
+    #[inline]
+    fn default() -> Self {
+        TypedIdSet::new_general()
+    }
+}
+
+impl<I, T: BitBlock, const N: usize> Eq for TypedIdSet<I, T, N> {}
+
+impl<I, T, const N: usize, const M: usize> PartialEq<TypedIdSet<I, T, M>> for TypedIdSet<I, T, N>
+    where T: BitBlock
+{
+    #[inline]
+    fn eq(&self, other: &TypedIdSet<I, T, M>) -> bool {
+        self.set == other.set
+    }
+}
+
+impl<I: IdLike, T: BitBlock, const N: usize> Extend<I> for TypedIdSet<I, T, N> {
+    #[inline]
+    fn extend<J: IntoIterator<Item = I>>(&mut self, iter: J) {
+        self.set.extend(iter.into_iter().map(I::to_index))
+    }
+}
+
+impl<I: IdLike, T: BitBlock, const N: usize> FromIterator<I> for TypedIdSet<I, T, N> {
+    #[inline]
+    fn from_iter<J: IntoIterator<Item = I>>(iter: J) -> Self {
+        TypedIdSet::from_untyped(iter.into_iter().map(I::to_index).collect())
+    }
+}
+
+impl<'a, I: IdLike, T: BitBlock, const N: usize> IntoIterator for &'a TypedIdSet<I, T, N> {
+    type Item = I;
+    type IntoIter = Iter<I, super::Iter<'a, T>>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+impl<I: IdLike, T: BitBlock, const N: usize> IntoIterator for TypedIdSet<I, T, N> {
+    type Item = I;
+    type IntoIter = Iter<I, super::IntoIter<T, N>>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        Iter::new(self.set.into_iter())
+    }
+}
+
+#[derive(Clone, Debug)]
+/// Converts an iterator over indices into an iterator over typed elements.
+pub struct Iter<I, J> {
+    inner: J,
+    marker: PhantomData<fn() -> I>,
+}
+
+impl<I, J> Iter<I, J> {
+    fn new(inner: J) -> Self {
+        Iter {
+            inner,
+            marker: PhantomData,
+        }
+    }
+}
+
+impl<I, J> Iterator for Iter<I, J>
+    where I: IdLike,
+          J: Iterator<Item = Id>
+{
+    type Item = I;
+
+    #[inline]
+    fn next(&mut self) -> Option<Self::Item> {
+        self.inner.next().map(I::from_index)
+    }
+
+    #[inline]
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inner.size_hint()
+    }
+}
+
+impl<I, J> DoubleEndedIterator for Iter<I, J>
+    where I: IdLike,
+          J: DoubleEndedIterator<Item = Id>
+{
+    #[inline]
+    fn next_back(&mut self) -> Option<Self::Item> {
+        self.inner.next_back().map(I::from_index)
+    }
+}
+
+impl<I, J> ExactSizeIterator for Iter<I, J>
+    where I: IdLike,
+          J: ExactSizeIterator<Item = Id>
+{
+    #[inline]
+    fn len(&self) -> usize {
+        self.inner.len()
+    }
+}
+
+#[derive(Clone, Debug)]
+/// A view into the blocks of a typed set or combination of typed sets. An iterator over the
+/// elements can be obtained with `into_iter()`.
+pub struct TypedBlockIter<I, B> {
+    inner: BlockIter<B>,
+    marker: PhantomData<fn() -> I>,
+}
+
+impl<I, B> TypedBlockIter<I, B>
+    where I: IdLike,
+          B: ExactSizeIterator,
+          B::Item: BitBlock
+{
+    #[inline]
+    /// Creates a typed block iterator from an untyped one, whose elements are the indices of
+    /// values of type `I`.
+    pub fn new(inner: BlockIter<B>) -> Self {
+        TypedBlockIter {
+            inner,
+            marker: PhantomData,
+        }
+    }
+
+    #[inline]
+    /// Returns the untyped block iterator.
+    pub fn into_untyped(self) -> BlockIter<B> {
+        self.inner
+    }
+
+    #[inline]
+    /// Equivalent to `self.into_iter().collect()`.
+    pub fn collect<C>(self) -> C
+        where C: FromIterator<I>
+    {
+        self.into_iter().collect()
+    }
+
+    #[inline]
+    /// Collects the iterator into a `TypedIdSet`.
+    pub fn into_set(self) -> TypedIdSet<I, B::Item> {
+        TypedIdSet::from_untyped(self.inner.into_set())
+    }
+
+    #[inline]
+    /// Returns the number of elements in the blocks, without iterating over the elements.
+    pub fn count_ones(self) -> usize {
+        self.inner.count_ones()
+    }
+}
+
+impl<I, B> IntoIterator for TypedBlockIter<I, B>
+    where I: IdLike,
+          B: ExactSizeIterator,
+          B::Item: BitBlock
+{
+    type Item = I;
+    type IntoIter = Iter<I, IdIter<B>>;
+
+    #[inline]
+    fn into_iter(self) -> Self::IntoIter {
+        Iter::new(self.inner.into_iter())
+    }
+}
+
+/// Conversion into an iterator over the blocks of a typed set. Only values with the same `Id`
+/// type can be combined with each other.
+pub trait IntoTypedBlockIterator {
+    /// The element type.
+    type Id: IdLike;
+
+    /// The untyped value, which holds the indices of the elements.
+    type Untyped: IntoBlockIterator;
+
+    /// Returns the untyped value.
+    fn into_untyped(self) -> Self::Untyped;
+}
+
+/// The raw block iterator type of a typed value.
+type UntypedBlocks<R> = <<R as IntoTypedBlockIterator>::Untyped as IntoBlockIterator>::Blocks;
+
+impl<I, B> IntoTypedBlockIterator for TypedBlockIter<I, B>
+    where I: IdLike,
+          B: ExactSizeIterator,
+          B::Item: BitBlock
+{
+    type Id = I;
+    type Untyped = BlockIter<B>;
+
+    #[inline]
+    fn into_untyped(self) -> Self::Untyped {
+        self.inner
+    }
+}
+
+impl<'a, I: IdLike, T: BitBlock, const N: usize> IntoTypedBlockIterator
+    for &'a TypedIdSet<I, T, N> {
+    type Id = I;
+    type Untyped = &'a IdSet<T, N>;
+
+    #[inline]
+    fn into_untyped(self) -> Self::Untyped {
+        &self.set
+    }
+}
+
+impl<I: IdLike, T: BitBlock, const N: usize> IntoTypedBlockIterator for TypedIdSet<I, T, N> {
+    type Id = I;
+    type Untyped = IdSet<T, N>;
+
+    #[inline]
+    fn into_untyped(self) -> Self::Untyped {
+        self.set
+    }
+}
+
+macro_rules! typed_ops {
+    ($($op:ident $method:ident $assign:ident $assign_method:ident $iter:ident,)*) => {$(
+        impl<I, B, R> ops::$op<R> for TypedBlockIter<I, B>
+            where I: IdLike,
+                  B: ExactSizeIterator,
+                  B::Item: BitBlock,
+                  R: IntoTypedBlockIterator<Id = I>,
+                  R::Untyped: IntoBlockIterator<Block = B::Item>
+        {
+            type Output = TypedBlockIter<I, $iter<B, UntypedBlocks<R>>>;
+
+            #[inline]
+            fn $method(self, other: R) -> Self::Output {
+                TypedBlockIter::new(ops::$op::$method(self.inner, other.into_untyped()))
+            }
+        }
+
+        impl<'a, I, T, R, const N: usize> ops::$op<R> for &'a TypedIdSet<I, T, N>
+            where I: IdLike,
+                  T: BitBlock,
+                  R: IntoTypedBlockIterator<Id = I>,
+                  R::Untyped: IntoBlockIterator<Block = T>
+        {
+            type Output = TypedBlockIter<I, $iter<Blocks<'a, T>, UntypedBlocks<R>>>;
+
+            #[inline]
+            fn $method(self, other: R) -> Self::Output {
+                TypedBlockIter::new(ops::$op::$method(&self.set, other.into_untyped()))
+            }
+        }
+
+        impl<I, T, R, const N: usize> ops::$op<R> for TypedIdSet<I, T, N>
+            where I: IdLike,
+                  T: BitBlock,
+                  R: IntoTypedBlockIterator<Id = I>,
+                  R::Untyped: IntoBlockIterator<Block = T>
+        {
+            type Output = TypedBlockIter<I, $iter<IntoBlocks<T, N>, UntypedBlocks<R>>>;
+
+            #[inline]
+            fn $method(self, other: R) -> Self::Output {
+                TypedBlockIter::new(ops::$op::$method(self.set, other.into_untyped()))
+            }
+        }
+
+        impl<I, T, R, const N: usize> ops::$assign<R> for TypedIdSet<I, T, N>
+            where I: IdLike,
+                  T: BitBlock,
+                  R: IntoTypedBlockIterator<Id = I>,
+                  R::Untyped: IntoBlockIterator<Block = T>
+        {
+            #[inline]
+            fn $assign_method(&mut self, other: R) {
+                ops::$assign::$assign_method(&mut self.set, other.into_untyped())
+            }
+        }
+    )*}
+}
+
+typed_ops! {
+    BitOr bitor BitOrAssign bitor_assign Union,
+    BitAnd bitand BitAndAssign bitand_assign Intersection,
+    BitXor bitxor BitXorAssign bitxor_assign SymmetricDifference,
+    Sub sub SubAssign sub_assign Difference,
+}
@@ -0,0 +1,164 @@
+use std::{cmp, error, fmt, io};
+use std::io::{Read, Write};
+
+use super::{count_ones, BitBlock, IdSet};
+use store::BlockStore;
+
+const MAGIC: [u8; 4] = *b"IDST";
+const VERSION: u8 = 1;
+const HEADER_LEN: usize = 22;
+
+/// The largest number of blocks allocated up front when reading, so a corrupt header cannot cause a
+/// huge allocation.
+const MAX_PREALLOC: usize = 1 << 16;
+
+#[derive(Debug)]
+/// An error reading a set in the binary format.
+pub enum ReadError {
+    /// An error from the underlying reader.
+    Io(io::Error),
+    /// The input ended before the end of the set.
+    Truncated,
+    /// The input does not start with the expected magic bytes.
+    BadMagic,
+    /// The format version is not supported.
+    UnsupportedVersion(u8),
+    /// The block width of the input does not match the block type of the set, in bits.
+    BlockWidth {
+        /// The block width of the set type.
+        expected: usize,
+        /// The block width of the input.
+        found: usize,
+    },
+    /// The element count in the header does not match the number of bits set in the blocks.
+    CountMismatch {
+        /// The element count in the header.
+        expected: u64,
+        /// The number of bits set in the blocks.
+        found: u64,
+    },
+    /// The set is too large to be represented on this platform.
+    TooLarge,
+}
+
+impl fmt::Display for ReadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match *self {
+            ReadError::Io(ref err) => write!(f, "{}", err),
+            ReadError::Truncated => write!(f, "unexpected end of input"),
+            ReadError::BadMagic => write!(f, "invalid magic bytes"),
+            ReadError::UnsupportedVersion(version) => {
+                write!(f, "unsupported format version {}", version)
+            }
+            ReadError::BlockWidth { expected, found } => {
+                write!(f, "expected {}-bit blocks, found {}-bit blocks", expected, found)
+            }
+            ReadError::CountMismatch { expected, found } => {
+                write!(f,
+                       "element count {} does not match the {} elements in the blocks",
+                       expected,
+                       found)
+            }
+            ReadError::TooLarge => write!(f, "set is too large for this platform"),
+        }
+    }
+}
+
+impl error::Error for ReadError {
+    fn source(&self) -> Option<&(dyn error::Error + 'static)> {
+        match *self {
+            ReadError::Io(ref err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<io::Error> for ReadError {
+    fn from(err: io::Error) -> Self {
+        if err.kind() == io::ErrorKind::UnexpectedEof {
+            ReadError::Truncated
+        } else {
+            ReadError::Io(err)
+        }
+    }
+}
+
+impl<T: BitBlock, const N: usize> IdSet<T, N> {
+    /// Writes the set in a stable, endian-independent binary format.
+    ///
+    /// The format consists of a 22-byte header followed by the blocks:
+    ///
+    /// | Offset | Size | Contents                                       |
+    /// |--------|------|------------------------------------------------|
+    /// | 0      | 4    | The magic bytes `IDST`                         |
+    /// | 4      | 1    | The format version, currently 1                |
+    /// | 5      | 1    | The block width in bytes                       |
+    /// | 6      | 8    | The element count as a little-endian `u64`     |
+    /// | 14     | 8    | The block count as a little-endian `u64`       |
+    /// | 22     | ...  | The blocks, each little-endian                 |
+    ///
+    /// Trailing zero blocks are not written.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        let bytes = T::BITS / 8;
+        let blocks = self.as_blocks();
+        let used = blocks.iter().rposition(|&block| block != T::ZERO).map_or(0, |idx| idx + 1);
+
+        let mut header = [0; HEADER_LEN];
+        header[..4].copy_from_slice(&MAGIC);
+        header[4] = VERSION;
+        header[5] = bytes as u8;
+        header[6..14].copy_from_slice(&(self.len as u64).to_le_bytes());
+        header[14..].copy_from_slice(&(used as u64).to_le_bytes());
+        writer.write_all(&header)?;
+
+        for block in &blocks[..used] {
+            writer.write_all(&block.to_u128().to_le_bytes()[..bytes])?;
+        }
+        Ok(())
+    }
+
+    /// Reads a set written by [`write_to`](#method.write_to). The element count is verified
+    /// against the blocks.
+    pub fn read_from<R: Read>(reader: &mut R) -> Result<Self, ReadError> {
+        let mut header = [0; HEADER_LEN];
+        reader.read_exact(&mut header)?;
+        if header[..4] != MAGIC {
+            return Err(ReadError::BadMagic);
+        }
+        if header[4] != VERSION {
+            return Err(ReadError::UnsupportedVersion(header[4]));
+        }
+        let bytes = header[5] as usize;
+        if bytes * 8 != T::BITS {
+            return Err(ReadError::BlockWidth {
+                           expected: T::BITS,
+                           found: bytes * 8,
+                       });
+        }
+        let mut word = [0; 8];
+        word.copy_from_slice(&header[6..14]);
+        let expected = u64::from_le_bytes(word);
+        word.copy_from_slice(&header[14..]);
+        let nblocks = u64::from_le_bytes(word);
+        if nblocks > (usize::MAX / T::BITS) as u64 {
+            return Err(ReadError::TooLarge);
+        }
+        let nblocks = nblocks as usize;
+
+        let mut blocks = Vec::with_capacity(cmp::min(nblocks, MAX_PREALLOC));
+        let mut buf = [0; 16];
+        for _ in 0..nblocks {
+            reader.read_exact(&mut buf[..bytes])?;
+            blocks.push(T::from_u128(u128::from_le_bytes(buf)));
+        }
+
+        let found = count_ones(&blocks) as u64;
+        if found != expected {
+            return Err(ReadError::CountMismatch { expected, found });
+        }
+        Ok(IdSet {
+               blocks: BlockStore::Heap(blocks),
+               len: found as usize,
+           })
+    }
+}